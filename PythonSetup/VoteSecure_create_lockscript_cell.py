@@ -257,15 +257,19 @@ class VoteSecureDeployer:
                 "Please ensure the Rust contract source is in ./contract/"
             )
         
-        # Compile with cargo
-        print("Running: cargo build --release --target=riscv64imac-unknown-none-elf")
+        # Compile with cargo; the contract's placeholder verifiers only
+        # build with the testnet feature
+        build_cmd = [
+            "cargo", "build",
+            "--release",
+            "--target=riscv64imac-unknown-none-elf"
+        ]
+        if not USE_MAINNET:
+            build_cmd += ["--features", "testnet"]
+        print(f"Running: {' '.join(build_cmd)}")
         
         result = subprocess.run(
-            [
-                "cargo", "build",
-                "--release",
-                "--target=riscv64imac-unknown-none-elf"
-            ],
+            build_cmd,
             cwd=contract_dir,
             capture_output=True,
             text=True
@@ -0,0 +1,153 @@
+//! Blake2b-256 with CKB's personalization
+//!
+//! CKB hashes transactions, scripts and lock args with blake2b-256 under the
+//! personal string "ckb-default-hash", so every digest the script compares
+//! against the chain has to use the same parameters. This is a plain RFC 7693
+//! implementation for that one parameter set: unkeyed, 32-byte output, no
+//! allocation and no C toolchain.
+
+/// Personal string CKB mixes into every blake2b-256 digest
+const PERSONALIZATION: &[u8; 16] = b"ckb-default-hash";
+
+const OUTPUT_SIZE: usize = 32;
+const BLOCK_SIZE: usize = 128;
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+// Message word schedule; rounds 10 and 11 reuse rows 0 and 1
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Hash `data` into `output` with CKB's blake2b-256
+pub fn blake2b_256(data: &[u8], output: &mut [u8; OUTPUT_SIZE]) {
+    let mut h = IV;
+    
+    // Parameter block: digest length, no key, fanout 1, depth 1, and the
+    // personal string in its last 16 bytes
+    h[0] ^= 0x0101_0000 ^ OUTPUT_SIZE as u64;
+    h[6] ^= read_word(&PERSONALIZATION[..8]);
+    h[7] ^= read_word(&PERSONALIZATION[8..]);
+    
+    // Every full block except the last is compressed as it comes; the last
+    // one (possibly empty or partial) gets the finalization flag
+    let mut offset = 0;
+    while data.len() - offset > BLOCK_SIZE {
+        compress(&mut h, &data[offset..offset + BLOCK_SIZE], (offset + BLOCK_SIZE) as u64, false);
+        offset += BLOCK_SIZE;
+    }
+    
+    let mut last = [0u8; BLOCK_SIZE];
+    last[..data.len() - offset].copy_from_slice(&data[offset..]);
+    compress(&mut h, &last, data.len() as u64, true);
+    
+    for (i, word) in h.iter().take(OUTPUT_SIZE / 8).enumerate() {
+        output[i * 8..i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+/// Read a little-endian u64 from an 8-byte slice
+fn read_word(bytes: &[u8]) -> u64 {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(bytes);
+    u64::from_le_bytes(word)
+}
+
+/// Mix one 128-byte block into the state; `counter` is the total number of
+/// message bytes hashed so far, including this block
+fn compress(h: &mut [u64; 8], block: &[u8], counter: u64, last: bool) {
+    let mut m = [0u64; 16];
+    for (i, word) in m.iter_mut().enumerate() {
+        *word = read_word(&block[i * 8..i * 8 + 8]);
+    }
+    
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    
+    // The high half of the 128-bit counter stays zero for any message the
+    // VM could hold
+    v[12] ^= counter;
+    if last {
+        v[14] = !v[14];
+    }
+    
+    for round in 0..12 {
+        let s = &SIGMA[round % 10];
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// The G mixing function
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn digest(data: &[u8]) -> [u8; OUTPUT_SIZE] {
+        let mut output = [0u8; OUTPUT_SIZE];
+        blake2b_256(data, &mut output);
+        output
+    }
+    
+    #[test]
+    fn empty_input_matches_ckbs_published_digest() {
+        let expected: [u8; OUTPUT_SIZE] = [
+            0x44, 0xf4, 0xc6, 0x97, 0x44, 0xd5, 0xf8, 0xc5, 0x5d, 0x64, 0x20, 0x62, 0x94, 0x9d, 0xca, 0xe4,
+            0x9b, 0xc4, 0xe7, 0xef, 0x43, 0xd3, 0x88, 0xc5, 0xa1, 0x2f, 0x42, 0xb5, 0x63, 0x3d, 0x16, 0x3e,
+        ];
+        assert!(digest(&[]) == expected);
+    }
+    
+    #[test]
+    fn block_boundaries_match_the_reference_implementation() {
+        let data: std::vec::Vec<u8> = (0..600u32).map(|i| (i * 7 + 3) as u8).collect();
+        
+        for len in [1, 32, 127, 128, 129, 255, 256, 257, 600] {
+            let reference = blake2b_simd::Params::new()
+                .hash_length(OUTPUT_SIZE)
+                .personal(PERSONALIZATION)
+                .hash(&data[..len]);
+            assert!(digest(&data[..len])[..] == reference.as_bytes()[..]);
+        }
+    }
+}
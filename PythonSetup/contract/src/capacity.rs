@@ -0,0 +1,71 @@
+//! Overflow-checked capacity arithmetic for EventFund accounting
+//!
+//! Capacities are u64 shannons, and a fund flow adds up one per cell. A sum
+//! that wrapped could make an overspend look balanced, so every EventFund
+//! total, difference and fee product goes through these helpers. Any
+//! overflow or underflow surfaces as `EventfundMisuse`.
+
+use crate::ContractError;
+
+/// A capacity computation left the u64 range
+pub struct CapacityError;
+
+impl From<CapacityError> for ContractError {
+    fn from(_: CapacityError) -> Self {
+        ContractError::EventfundMisuse
+    }
+}
+
+/// Sum capacities, failing instead of wrapping
+pub fn checked_sum(amounts: impl IntoIterator<Item = u64>) -> Result<u64, CapacityError> {
+    let mut total = 0u64;
+    
+    for amount in amounts {
+        total = match total.checked_add(amount) {
+            Some(t) => t,
+            None => return Err(CapacityError),
+        };
+    }
+    
+    Ok(total)
+}
+
+/// Subtract `amount` from `total`, failing if it would go below zero
+pub fn checked_sub(total: u64, amount: u64) -> Result<u64, CapacityError> {
+    match total.checked_sub(amount) {
+        Some(t) => Ok(t),
+        None => Err(CapacityError),
+    }
+}
+
+/// Total of `count` fees of `fee` each
+pub fn checked_fees(fee: u64, count: u64) -> Result<u64, CapacityError> {
+    match fee.checked_mul(count) {
+        Some(t) => Ok(t),
+        None => Err(CapacityError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn sums_and_differences_stay_exact_in_range() {
+        assert!(matches!(checked_sum([1, 2, u64::MAX - 3]), Ok(u64::MAX)));
+        assert!(matches!(checked_sum([]), Ok(0)));
+        assert!(matches!(checked_sub(10, 10), Ok(0)));
+        assert!(matches!(checked_fees(u64::MAX, 1), Ok(u64::MAX)));
+        assert!(matches!(checked_fees(0, u64::MAX), Ok(0)));
+    }
+    
+    #[test]
+    fn overflow_and_underflow_fail_as_fund_misuse() {
+        assert!(checked_sum([u64::MAX, 1]).is_err());
+        assert!(checked_sub(0, 1).is_err());
+        assert!(checked_fees(u64::MAX / 2 + 1, 2).is_err());
+        
+        let error: ContractError = CapacityError.into();
+        assert!(error == ContractError::EventfundMisuse);
+    }
+}
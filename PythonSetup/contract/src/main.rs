@@ -2,13 +2,21 @@
 //! 
 //! This contract validates all VoteSecure operations on CKB blockchain:
 //! - EventFund spending control (ballot payments and organizer withdrawal)
-//! - Ballot submission with eligibility verification
+//! - Optional voter registration in a window before voting opens
+//! - Ballot submission with eligibility verification (public, invite key,
+//!   curated list, token gated)
 //! - Schedule enforcement (voting window and audit period)
-//! - Revoting limits
+//! - Delegated (proxy) voting on the voter's signed authorization
+//! - Revoting limits and ballot revocation
 //! - Result release at voting_end with multisig
+//! - Threshold (t-of-n) trustee decryption of the revealed ballots
+//! - Result amendment during the audit period by every signer
 //! - Cell cleanup after audit period (audit_end_time)
+//! - Optional recovery-key sweep of a fund stranded by a lost organizer key
 //! - K-anonymity enforcement
 //! - Metadata cleanup after audit period
+//! - Event pause/resume and audit period extension by signer quorum
+//! - Read-only ballot count queries for auditors
 //! 
 //! Timeline:
 //! - voting_start → voting_end: Voting period (ballots accepted)
@@ -16,78 +24,198 @@
 //! - voting_end → audit_end_time: Audit period (verification, disputes)
 //! - audit_end_time: Fund withdrawal + cell cleanup allowed
 //! 
+//! Every schedule check, cleanup included, reads the time from the header
+//! of the block that created the guarded input, so transactions must list
+//! that block as a header dep. Without it the script exits with
+//! `HeaderMissing` (-16) rather than a generic syscall error. Spending a
+//! cell created long before, such as a fund withdrawn after the audit
+//! period, needs an absolute `since` on that input to date it later.
+//! 
+//! Scans come in two scopes. Group scans (`SOURCE_GROUP_INPUT`) see only
+//! the cells this script instance guards: the group binding check, fund
+//! capacity accounting and the prior state behind a chain link. Global
+//! scans over all inputs, outputs and cell deps are deliberate cross-cell
+//! checks: metadata lookup, the paying EventFund, ballot counts across
+//! every voter lock, tallies, and outputs matched back to our lock hash.
+//! 
 //! Author: VoteSecure Team
 //! Version: 2.1.0
 
-#![no_std]
-#![no_main]
+// Unit tests run on the host with the standard test harness
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+// The test harness has no `_start`, so most of the script is unreachable there
+#![cfg_attr(test, allow(dead_code))]
 
+// Host-side tooling only; the on-chain build leaves this feature off
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(target_arch = "riscv64")]
 use core::arch::asm;
 
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::{Signature, VerifyingKey};
+
+mod blake2b;
+mod capacity;
+#[cfg(any(test, feature = "std"))]
+mod metadata_encoder;
+#[cfg(test)]
+mod mock_chain;
+
+// The Schnorr, BLS, ranking proof and trustee share verifiers are still
+// placeholders that accept any well-formed input. Only testnet builds may
+// use them; `std` tooling builds need the feature too.
+#[cfg(not(feature = "testnet"))]
+compile_error!("placeholder crypto is enabled (verify_schnorr_signature, verify_bls_aggregate, verify_ranking_proof, verify_share_proof): build with `--features testnet`, or replace them with real implementations before a mainnet build");
+
+// Whether features backed by a placeholder verifier may be used. Off outside
+// testnet builds, so each stays disabled until its verifier is real even
+// after the compile-time gate above is lifted.
+const PLACEHOLDER_VERIFIERS: bool = cfg!(feature = "testnet");
+
 // ============================================================================
 // CKB Syscall Numbers
 // ============================================================================
 
 const SYS_EXIT: u64 = 93;
+const SYS_LOAD_TX_HASH: u64 = 2061;
 const SYS_LOAD_SCRIPT: u64 = 2051;
 const SYS_LOAD_CELL_BY_FIELD: u64 = 2072;
-const SYS_LOAD_INPUT_BY_FIELD: u64 = 2073;
 const SYS_LOAD_HEADER_BY_FIELD: u64 = 2074;
+const SYS_LOAD_INPUT_BY_FIELD: u64 = 2083;
 const SYS_LOAD_WITNESS: u64 = 2081;
-const SYS_LOAD_TRANSACTION: u64 = 2051;
 
-// Crypto syscall for secp256k1 signature verification
+// Cell data has its own syscall; it isn't one of the LOAD_CELL_BY_FIELD fields
 const SYS_LOAD_CELL_DATA: u64 = 2092;
 
+// Cycle counter and debug output, used by bench builds only
+#[cfg(feature = "bench")]
+const SYS_CURRENT_CYCLES: u64 = 2042;
+#[cfg(feature = "bench")]
+const SYS_DEBUG: u64 = 2177;
+
 // Field types for load operations
 const SOURCE_INPUT: u64 = 1;
 const SOURCE_OUTPUT: u64 = 2;
 const SOURCE_CELL_DEP: u64 = 3;
-#[allow(dead_code)]
+// Group sources only cover cells sharing this script. A lock script group
+// never has outputs, so our outputs are found by lock hash instead
 const SOURCE_GROUP_INPUT: u64 = 0x0100000000000001;
 #[allow(dead_code)]
 const SOURCE_GROUP_OUTPUT: u64 = 0x0100000000000002;
 
 const CELL_FIELD_CAPACITY: u64 = 0;
-const CELL_FIELD_DATA: u64 = 1;
+// CKB's data hash field; lets large cells be matched without loading them
+const CELL_FIELD_DATA_HASH: u64 = 1;
 const CELL_FIELD_LOCK: u64 = 2;
 const CELL_FIELD_LOCK_HASH: u64 = 3;
-const CELL_FIELD_TYPE: u64 = 4;
-#[allow(dead_code)]
 const CELL_FIELD_TYPE_HASH: u64 = 5;
+const CELL_FIELD_OCCUPIED_CAPACITY: u64 = 6;
 
+const HEADER_FIELD_EPOCH_NUMBER: u64 = 0;
 const HEADER_FIELD_TIMESTAMP: u64 = 5;
 
+const INPUT_FIELD_SINCE: u64 = 1;
+
+// Since values: a relative flag and a metric in the top byte, the value
+// below it. Only absolute timestamp and epoch values bound the clock
+const SINCE_RELATIVE_FLAG: u64 = 1 << 63;
+const SINCE_METRIC_MASK: u64 = 0x6000_0000_0000_0000;
+const SINCE_METRIC_EPOCH: u64 = 0x2000_0000_0000_0000;
+const SINCE_METRIC_TIMESTAMP: u64 = 0x4000_0000_0000_0000;
+const SINCE_VALUE_MASK: u64 = 0x00FF_FFFF_FFFF_FFFF;
+// An epoch since packs [number: 24 bits][index: 16][length: 16]
+const SINCE_EPOCH_NUMBER_MASK: u64 = 0x00FF_FFFF;
+
+// Syscall return codes
+const SYSCALL_INDEX_OUT_OF_BOUND: u64 = 1;
+const SYSCALL_ITEM_MISSING: u64 = 2;
+
 // ============================================================================
 // Error Codes
 // ============================================================================
 
 const SUCCESS: i8 = 0;
-const ERROR_INVALID_ARGS: i8 = -1;
-const ERROR_ENCODING: i8 = -2;
-const ERROR_SYSCALL: i8 = -3;
-const ERROR_METADATA_NOT_FOUND: i8 = -4;
-const ERROR_INVALID_TIMING: i8 = -5;
-const ERROR_VOTER_INELIGIBLE: i8 = -6;
-const ERROR_REVOTE_LIMIT_EXCEEDED: i8 = -7;
-const ERROR_TIMELOCK_NOT_EXPIRED: i8 = -8;
-const ERROR_INSUFFICIENT_SIGNATURES: i8 = -9;
-const ERROR_UNAUTHORIZED_WITHDRAWAL: i8 = -10;
-const ERROR_EVENTFUND_MISUSE: i8 = -11;
-const ERROR_METADATA_IMMUTABLE: i8 = -12;
-const ERROR_K_ANONYMITY_VIOLATION: i8 = -13;
-#[allow(dead_code)]
-const ERROR_INVALID_TALLY: i8 = -14;
-const ERROR_INVALID_SIGNATURE: i8 = -15;
+/// Contract failure reasons
+///
+/// The discriminants are the script exit codes and are part of the ABI:
+/// never renumber an existing variant, only append new ones.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(i8)]
+enum ContractError {
+    InvalidArgs = -1,
+    Encoding = -2,
+    Syscall = -3,
+    MetadataNotFound = -4,
+    InvalidTiming = -5,
+    VoterIneligible = -6,
+    RevoteLimitExceeded = -7,
+    TimelockNotExpired = -8,
+    InsufficientSignatures = -9,
+    UnauthorizedWithdrawal = -10,
+    EventfundMisuse = -11,
+    MetadataImmutable = -12,
+    KAnonymityViolation = -13,
+    InvalidTally = -14,
+    InvalidSignature = -15,
+    HeaderMissing = -16,
+    QuorumNotMet = -17,
+    BrokenChain = -18,
+    InvalidSequence = -19,
+    EmergencyHalt = -20,
+    EventPaused = -21,
+    InvalidReveal = -22,
+    DuplicateResult = -23,
+    AmbiguousMetadata = -24,
+    FrontendMismatch = -25,
+    InsufficientDeposit = -26,
+    FundLockedAudit = -27,
+    ResultsNotFinalized = -28,
+    EmptyWitness = -29,
+    InsufficientShares = -30,
+    SignerMismatch = -31,
+    BallotCommitmentMismatch = -32,
+    AmbiguousAttestation = -33,
+    InvalidMetadata = -34,
+}
+
+impl ContractError {
+    /// Exit code reported for this error
+    fn code(self) -> i8 {
+        self as i8
+    }
+}
 
 // ============================================================================
 // Cell Type Identifiers
 // ============================================================================
 
+// EventFund data: [type: 1][event_id: 32][organizer_lock_hash: 20]
 const EVENTFUND_TYPE: u8 = 0x00;
 const METADATA_TYPE: u8 = 0x01;
 const VOTER_TYPE: u8 = 0x02;
 const RESULT_TYPE: u8 = 0x03;
+// Append-only record of an operation; never spendable
+const AUDITLOG_TYPE: u8 = 0x04;
+const TURNOUT_ATTESTATION_TYPE: u8 = 0x05;
+// Read-only audit query; never guards a real cell
+const QUERY_TYPE: u8 = 0x06;
+// Proof a voter registered, held under the voter's own lock:
+// [type: 1][event_id: 32][voter_hash: 20][ballot_commitment: 32?]
+// [voter signer entry: 97]
+const REGISTRATION_TYPE: u8 = 0x07;
+
+// Script args layout versions (leading byte of the args)
+const ARGS_VERSION_0: u8 = 0;
+const ARGS_VERSION_1: u8 = 1;
+// Unversioned args from before the version byte: [type][event_id][voter_hash?]
+const LEGACY_ARGS_SIZE: usize = 1 + EVENT_ID_SIZE;
+const LEGACY_VOTER_ARGS_SIZE: usize = LEGACY_ARGS_SIZE + PUBKEY_HASH_SIZE;
+// Version 1 args extension tags with this bit set may be skipped by a
+// script that doesn't know them; any other unknown tag is rejected
+const ARGS_EXT_OPTIONAL: u8 = 0x80;
 
 // ============================================================================
 // Constants
@@ -104,10 +232,265 @@ const TIMESTAMP_SIZE: usize = 8;
 const ELIGIBILITY_PUBLIC: u8 = 0;
 const ELIGIBILITY_INVITE_KEY: u8 = 1;
 const ELIGIBILITY_CURATED_LIST: u8 = 2;
+const ELIGIBILITY_TOKEN_GATED: u8 = 3;
+// Invite mode proven by one BLS aggregate of many invite approvals
+const ELIGIBILITY_INVITE_BLS: u8 = 4;
+
+// WitnessArgs molecule header: total_size + 3 field offsets
+const WITNESS_ARGS_HEADER_SIZE: usize = 16;
+
+// Script molecule: [total_size: 4][offsets: 3 x 4][code_hash: 32]
+// [hash_type: 1][args: [len: 4][bytes]]
+const SCRIPT_CODE_HASH_OFFSET: usize = 16;
+const SCRIPT_HASH_TYPE_OFFSET: usize = 48;
+const SCRIPT_ARGS_OFFSET: usize = 49;
+const SCRIPT_HEADER_SIZE: usize = SCRIPT_ARGS_OFFSET + 4;
+// Longest args any VoteSecure lock may carry
+const MAX_SCRIPT_ARGS_SIZE: usize = 128;
+const MAX_SCRIPT_SIZE: usize = SCRIPT_HEADER_SIZE + MAX_SCRIPT_ARGS_SIZE;
 
 // Blake2b hash output size
 const BLAKE2B_HASH_SIZE: usize = 32;
 
+// Query exit codes: 1 + ballot count, saturating at QUERY_MAX_EXIT_CODE
+const QUERY_MAX_EXIT_CODE: i8 = 127;
+
+// Identifying prefix shared by all cell types: [type: 1][event_id: 32][voter_hash: 20]
+const CELL_PREFIX_SIZE: usize = 1 + EVENT_ID_SIZE + PUBKEY_HASH_SIZE;
+
+// Metadata variable-length layout
+const METADATA_MIN_SIZE: usize = 82;
+// Every metadata load uses this buffer size; larger cells are rejected
+const MAX_METADATA_SIZE: usize = 2048;
+const METADATA_FIXED_SIZE: usize = 114;
+const METADATA_AUDIT_END_OFFSET: usize = 69;
+const METADATA_ELIGIBILITY_MODE_OFFSET: usize = 77;
+const MAX_AUTHORIZED_SIGNERS: usize = 64;
+const MAX_VOTE_OPTIONS: usize = 32;
+const OPTION_CAP_SIZE: usize = 4;
+// Outputs scanned for ballots paid from the EventFund in one transaction
+const MAX_BATCH_BALLOTS: usize = 16;
+
+// Metadata extension records: [tag: u8][len: u8][value: len]
+const METADATA_EXT_FLAGS: u8 = 0x01;
+const METADATA_EXT_ELIGIBLE_VOTERS: u8 = 0x02;
+const METADATA_EXT_INVITE_KEY: u8 = 0x03;
+const METADATA_EXT_QUORUM: u8 = 0x04;
+const METADATA_EXT_BALLOT_FEE: u8 = 0x05;
+const METADATA_EXT_BREAKGLASS_KEY: u8 = 0x06;
+const METADATA_EXT_TOKEN_TYPE_HASH: u8 = 0x07;
+const METADATA_EXT_PAUSED: u8 = 0x08;
+const METADATA_EXT_REVEAL_KEY_HASH: u8 = 0x09;
+const METADATA_EXT_SUBMISSION_GRACE: u8 = 0x0A;
+const METADATA_EXT_SIGNATURE_SCHEME: u8 = 0x0B;
+const METADATA_EXT_MIN_BALLOT_DEPOSIT: u8 = 0x0C;
+const METADATA_EXT_BALLOT_ENCRYPTION: u8 = 0x0D;
+const METADATA_EXT_BLS_AGGREGATE_PUBKEY: u8 = 0x0E;
+const METADATA_EXT_REVOTE_MODE: u8 = 0x0F;
+const METADATA_EXT_BALLOT_FORMAT: u8 = 0x10;
+const METADATA_EXT_MAX_PARTICIPANTS: u8 = 0x11;
+const METADATA_EXT_COHORT_K_THRESHOLD: u8 = 0x12;
+const METADATA_EXT_TRUSTEE_SHARES: u8 = 0x13;
+const METADATA_EXT_REGISTRATION_WINDOW: u8 = 0x14;
+const METADATA_EXT_CANDIDATE_REGISTRY: u8 = 0x15;
+const METADATA_EXT_RECOVERY: u8 = 0x16;
+const METADATA_EXT_ORGANIZER_LOCK: u8 = 0x17;
+const METADATA_EXT_SUBMISSION_GRACE_EPOCHS: u8 = 0x18;
+
+// Revote semantics (METADATA_EXT_REVOTE_MODE)
+// Append: a revote adds a cell, bounded only by max_revotes
+const REVOTE_MODE_APPEND: u8 = 0;
+// Replace: a revote must consume the voter's prior cell
+const REVOTE_MODE_REPLACE: u8 = 1;
+
+// Metadata flag bits (METADATA_EXT_FLAGS, u32)
+const FLAG_INSTANT_FINALIZE: u32 = 1 << 0;
+const FLAG_ABSTAIN_COUNTS_FOR_QUORUM: u32 = 1 << 1;
+const FLAG_AUDIT_CHAIN: u32 = 1 << 2;
+const FLAG_PRIVACY_MODE: u32 = 1 << 3;
+const FLAG_EMERGENCY_HALT: u32 = 1 << 4;
+const FLAG_WEIGHTED_VOTING: u32 = 1 << 5;
+const FLAG_SEAL_UNTIL_K: u32 = 1 << 6;
+// Schedule times are epoch numbers instead of header timestamps
+const FLAG_EPOCH_SCHEDULE: u32 = 1 << 7;
+// Ballots must reference the approved frontend build as a cell dep
+const FLAG_PIN_FRONTEND: u32 = 1 << 8;
+// Result stores a Merkle root of the tallies instead of the tallies
+const FLAG_TALLY_COMMITMENT: u32 = 1 << 9;
+// Ballots name the EventFund input that paid for them
+const FLAG_FUND_BINDING: u32 = 1 << 10;
+// Ballots carry a cohort id for per-cohort k-anonymity
+const FLAG_COHORT_ID: u32 = 1 << 11;
+// Voters may delegate casting their ballot to a proxy key
+const FLAG_DELEGATION: u32 = 1 << 12;
+// Transactions may not carry another event's VoteSecure cells
+const FLAG_STRICT_TX: u32 = 1 << 13;
+
+// Schedule times are little-endian like every other integer. These
+// bounds (2200-01-01 in ms, and ~7600 years of 4-hour epochs) are far past
+// any real event but far below a byte-swapped value, so a big-endian
+// encoding is rejected instead of read as a schedule nobody can reach
+const MAX_SCHEDULE_TIMESTAMP: u64 = 7_258_118_400_000;
+const MAX_SCHEDULE_EPOCH: u64 = 1 << 24;
+
+// A registration may commit to blake2b(encrypted ballot) of the first ballot,
+// and ends with the voter's signer entry over its contents
+const REGISTRATION_COMMITMENT_OFFSET: usize = CELL_PREFIX_SIZE;
+const REGISTRATION_BARE_SIZE: usize = CELL_PREFIX_SIZE + SIGNER_ENTRY_SIZE;
+const REGISTRATION_COMMITTED_SIZE: usize = REGISTRATION_BARE_SIZE + BLAKE2B_HASH_SIZE;
+
+// Largest event allowed to skip the audit window
+const INSTANT_FINALIZE_MAX_VOTERS: u32 = 25;
+
+// Result cell layout
+const RESULT_TOTAL_VOTES_OFFSET: usize = 33;
+const RESULT_SIGNATURE_COUNT_OFFSET: usize = 37;
+const RESULT_TALLY_COUNT_OFFSET: usize = 38;
+const RESULT_TALLIES_OFFSET: usize = 39;
+// With FLAG_TALLY_COMMITMENT the root takes the place of the tallies
+const RESULT_TALLY_ROOT_OFFSET: usize = RESULT_TALLIES_OFFSET;
+const TALLY_SIZE: usize = 4;
+// A release consumes a seed cell holding only [type][event_id]; a cell
+// long enough to carry totals is a released result being amended
+const RESULT_RELEASED_MIN_SIZE: usize = RESULT_TALLY_COUNT_OFFSET;
+// Amendment reason codes; any nonzero code is accepted and stays in the
+// witness for auditors
+const AMENDMENT_REASON_NONE: u8 = 0x00;
+
+// Voter cell versions sit in the high nibble of the type tag, so the
+// [type][event_id][voter_hash] prefix keeps its offsets in every version.
+// Version 0 is the layout below and the only one defined so far.
+const CELL_TYPE_MASK: u8 = 0x0F;
+const VOTER_VERSION_0: u8 = 0;
+
+// Voter ballot layout
+const BALLOT_SEQUENCE_OFFSET: usize = 53;
+const BALLOT_TIMESTAMP_OFFSET: usize = 57;
+const BALLOT_PAYLOAD_OFFSET: usize = 65;
+// Default (and largest) payload; events may declare a shorter ciphertext,
+// which moves the weight and everything after it
+const BALLOT_PAYLOAD_SIZE: usize = 256;
+const BALLOT_COMMITMENT_SIZE: usize = 32;
+const BALLOT_WEIGHT_OFFSET: usize = BALLOT_PAYLOAD_OFFSET + BALLOT_PAYLOAD_SIZE;
+// Seal byte after the commitment: sealed ballots need the event key to decode
+const BALLOT_SEAL_OFFSET: usize = BALLOT_PAYLOAD_OFFSET + BALLOT_COMMITMENT_SIZE;
+const BALLOT_SEALED: u8 = 0x00;
+const BALLOT_DECODABLE: u8 = 0x01;
+// With FLAG_FUND_BINDING: [fund_input_index: u32] after the weight
+const FUND_REF_SIZE: usize = 4;
+// With FLAG_COHORT_ID: [cohort_id: u8] after the fund reference, where
+// cohort 0 is unsegmented and only counts toward the global threshold
+const COHORT_ID_SIZE: usize = 1;
+const COHORT_UNSEGMENTED: u8 = 0;
+
+// Ballot formats (METADATA_EXT_BALLOT_FORMAT: [format: u8][candidate_count: u8])
+// METADATA_EXT_CANDIDATE_REGISTRY ([candidate_count: u16]) sets the same count
+// for any format, bounding the options a revealed ballot may select
+const BALLOT_FORMAT_SINGLE: u8 = 0;
+const BALLOT_FORMAT_RANKED: u8 = 1;
+// Ranked payloads follow the seal byte with [rank_count: u8][ranking_proof: 64]
+const BALLOT_RANK_COUNT_OFFSET: usize = BALLOT_SEAL_OFFSET + 1;
+const BALLOT_RANKING_PROOF_OFFSET: usize = BALLOT_RANK_COUNT_OFFSET + 1;
+const RANKING_PROOF_SIZE: usize = 64;
+const MIN_RANKED_CIPHERTEXT_LEN: usize = BALLOT_RANKING_PROOF_OFFSET + RANKING_PROOF_SIZE - BALLOT_PAYLOAD_OFFSET;
+
+// Curated list leaf: [voter_hash: 20][max_weight: u32]
+const CURATED_LEAF_SIZE: usize = PUBKEY_HASH_SIZE + 4;
+
+// Tally reveal entry in the release witness: [option: u8][salt: 32]
+const TALLY_REVEAL_SALT_SIZE: usize = 32;
+const TALLY_REVEAL_ENTRY_SIZE: usize = 1 + TALLY_REVEAL_SALT_SIZE;
+// Revealed option marking an abstention (counted in no tally)
+const ABSTAIN_OPTION: u8 = 0xFF;
+// Signature domain tags. Every signed message is blake2b(tag || ...), so
+// a signature gathered for one operation is never valid for another:
+// ballot, invite approval, registration, revocation, fund withdrawal or
+// setup, metadata and result cleanup, break-glass halt, signer quorum
+// metadata updates, result release, and recovery of a stranded fund.
+// Messages over event data rather than a transaction are tagged too:
+// delegations, BLS invites and turnout attestations.
+const DOMAIN_BALLOT: &[u8] = b"VS_BALLOT_V1";
+const DOMAIN_INVITE: &[u8] = b"VS_INVITE_V1";
+const DOMAIN_INVITE_BLS: &[u8] = b"VS_INVITE_BLS_V1";
+const DOMAIN_DELEGATE: &[u8] = b"VS_DELEGATE_V1";
+const DOMAIN_ATTEST: &[u8] = b"VS_ATTEST_V1";
+const DOMAIN_REGISTER: &[u8] = b"VS_REGISTER_V1";
+const DOMAIN_REVOKE: &[u8] = b"VS_REVOKE_V1";
+const DOMAIN_WITHDRAW: &[u8] = b"VS_WITHDRAW_V1";
+const DOMAIN_CLEANUP: &[u8] = b"VS_CLEANUP_V1";
+const DOMAIN_HALT: &[u8] = b"VS_HALT_V1";
+const DOMAIN_UPDATE: &[u8] = b"VS_UPDATE_V1";
+const DOMAIN_RELEASE: &[u8] = b"VS_RELEASE_V1";
+const DOMAIN_RECOVER: &[u8] = b"VS_RECOVER_V1";
+const MAX_DOMAIN_TAG_SIZE: usize = 16;
+
+// Ballot decryption key revealed in the release witness
+const REVEAL_KEY_SIZE: usize = 32;
+// Threshold events reveal key shares instead of the key:
+// [share_count: u8][[trustee_index: u8][share: 32][proof: 64]]...
+// METADATA_EXT_TRUSTEE_SHARES: [threshold: u8][share_pubkey_commitment: 32]...
+const MAX_TRUSTEES: usize = 7;
+const SHARE_COMMITMENT_SIZE: usize = 32;
+const SHARE_PROOF_SIZE: usize = 64;
+const SHARE_ENTRY_SIZE: usize = 1 + REVEAL_KEY_SIZE + SHARE_PROOF_SIZE;
+
+// Audit log layout: [type][event_id][op_code: u8][timestamp: u64][actor_hash: 20]
+const AUDITLOG_OP_OFFSET: usize = 33;
+const AUDITLOG_TIMESTAMP_OFFSET: usize = 34;
+const AUDITLOG_ACTOR_OFFSET: usize = 42;
+const AUDITLOG_SIZE: usize = AUDITLOG_ACTOR_OFFSET + PUBKEY_HASH_SIZE;
+
+// Audit log operation codes
+const AUDIT_OP_BALLOT: u8 = 0x01;
+const AUDIT_OP_REVOTE: u8 = 0x02;
+const AUDIT_OP_RELEASE: u8 = 0x03;
+const AUDIT_OP_WITHDRAWAL: u8 = 0x04;
+
+// Turnout attestation layout
+const ATTESTATION_VOTER_COUNT_OFFSET: usize = 33;
+const ATTESTATION_BALLOT_ROOT_OFFSET: usize = 37;
+const ATTESTATION_BODY_SIZE: usize = 69;
+const SIGNER_ENTRY_SIZE: usize = PUBKEY_SIZE + SIGNATURE_SIZE;
+// Largest predecessor cell an audit chain link can cover
+const MAX_CHAINED_CELL_SIZE: usize = 2048;
+// Largest reveal a release witness may carry after its signatures
+const MAX_REVEAL_SIZE: usize = 2048;
+// Body, signature count and an entry from every authorized signer
+const MAX_ATTESTATION_SIZE: usize = ATTESTATION_BODY_SIZE + 1 + MAX_AUTHORIZED_SIGNERS * SIGNER_ENTRY_SIZE;
+// Delegated voter witness: [proxy entry][voter delegation entry]
+const DELEGATED_WITNESS_SIZE: usize = 2 * SIGNER_ENTRY_SIZE;
+// Invite witness: [voter entry][invite issuer entry]
+const INVITE_WITNESS_SIZE: usize = 2 * SIGNER_ENTRY_SIZE;
+// A voter's whole WitnessArgs: the largest voter witness lock plus room
+// for the table header and any input or output type fields
+const VOTER_WITNESS_BUFFER_SIZE: usize = 512;
+
+// Ballot encryption schemes (METADATA_EXT_BALLOT_ENCRYPTION)
+// 0 = default envelope, 1 = ElGamal, 2 = RSA
+const ENCRYPTION_SCHEME_DEFAULT: u8 = 0;
+const ENCRYPTION_SCHEME_RSA: u8 = 2;
+// Smallest ciphertext: the payload must hold the commitment and seal byte
+const MIN_CIPHERTEXT_LEN: usize = BALLOT_COMMITMENT_SIZE + 1;
+
+// Multisig signature schemes
+const SIGNATURE_SCHEME_ECDSA: u8 = 0;
+const SIGNATURE_SCHEME_SCHNORR: u8 = 1;
+const XONLY_PUBKEY_SIZE: usize = 32; // BIP340 x-only public key
+
+// BLS12-381 (min-pubkey-size): compressed G1 keys, compressed G2 signatures
+const BLS_PUBKEY_SIZE: usize = 48;
+const BLS_SIGNATURE_SIZE: usize = 96;
+// Aggregate invite witness: [voter entry][aggregate signature]
+const BLS_INVITE_WITNESS_SIZE: usize = SIGNER_ENTRY_SIZE + BLS_SIGNATURE_SIZE;
+
+// Half the secp256k1 curve order, big-endian (upper bound for low-s)
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x5D, 0x57, 0x6E, 0x73, 0x57, 0xA4, 0x50, 0x1D,
+    0xDF, 0xE9, 0x2F, 0x46, 0x68, 0x1B, 0x20, 0xA0,
+];
+
 // ============================================================================
 // Data Structures
 // ============================================================================
@@ -122,13 +505,16 @@ struct EventMetadata {
     voting_end: u64,
     audit_end_time: u64,  // After this: withdrawal + cell cleanup allowed
     eligibility_mode: u8,
-    max_revotes: u8,
+    max_revotes: u8,      // Revotes after the first ballot; 255 = unlimited
     required_signatures: u8,
     k_anonymity_threshold: u16,
     frontend_code_hash: [u8; 32],
     // Variable length data follows:
-    // - eligibility_data (if curated list mode)
-    // - authorized_signers[] (pubkey hashes)
+    // - eligibility_len: u16 + eligibility_data (curated list mode only,
+    //   leaves of [voter_hash: 20][max_weight: u32])
+    // - signer_count: u8 + authorized_signers[] (pubkey hashes)
+    // - option_count: u8 + option_caps[] (u32 per option, 0 = uncapped)
+    // - extension records [tag: u8][len: u8][value] until end of cell
 }
 
 /// Voter ballot structure
@@ -139,7 +525,9 @@ struct VoterBallot {
     voter_pubkey_hash: [u8; PUBKEY_HASH_SIZE],
     sequence_number: u32,
     timestamp: u64,
-    encrypted_ballot: [u8; 256], // Fixed size for MVP
+    encrypted_ballot: [u8; 256], // Fixed size for MVP; starts with blake2b(option || salt) and a seal byte
+    // weight: u32 follows for weighted voting events
+    // prev_state_hash: [u8; 32] follows for audit chain events
 }
 
 /// Result cell structure
@@ -150,8 +538,33 @@ struct ResultCell {
     total_votes: u32,
     release_signatures_count: u8,
     // Variable length:
-    // - vote_tallies[]
+    // - tally_count: u8 + vote_tallies[] (u32 per option), or
+    //   tally_count: u8 + tally_root: [u8; 32] with FLAG_TALLY_COMMITMENT
     // - release_signatures[]
+    // - prev_state_hash: [u8; 32] (audit chain events only)
+}
+
+/// Turnout attestation cell structure (carried as a cell dep)
+#[repr(C)]
+#[allow(dead_code)]
+struct TurnoutAttestation {
+    event_id: [u8; EVENT_ID_SIZE],
+    voter_count: u32,
+    // Hash chain over the attested ballots, see `compute_ballot_root`
+    ballot_root: [u8; 32],
+    // Variable length:
+    // - sig_count: u8 + signatures[] ([pubkey: 33][sig: 64], over
+    //   blake2b(DOMAIN_ATTEST || body))
+}
+
+/// Audit log cell structure
+#[repr(C)]
+#[allow(dead_code)]
+struct AuditLogCell {
+    event_id: [u8; EVENT_ID_SIZE],
+    op_code: u8,
+    timestamp: u64,
+    actor_hash: [u8; PUBKEY_HASH_SIZE],
 }
 
 /// Parsed metadata for validation
@@ -166,6 +579,134 @@ struct ParsedMetadata {
     max_revotes: u8,
     required_signatures: u8,
     k_anonymity_threshold: u16,
+    // All zero for metadata shorter than the fixed core
+    frontend_code_hash: [u8; BLAKE2B_HASH_SIZE],
+    // Extension fields (defaults apply when the record is absent)
+    flags: u32,
+    eligible_voter_count: u32,
+    invite_key_hash: [u8; PUBKEY_HASH_SIZE],
+    quorum: u32,
+    ballot_fee: u64,
+    breakglass_hash: [u8; PUBKEY_HASH_SIZE],
+    // Offset of the flags value within the cell (0 if no flags record)
+    flags_offset: usize,
+    signer_count: usize,
+    token_type_hash: [u8; 32],
+    paused: u8,
+    // Offset of the paused byte within the cell (0 if no paused record)
+    paused_offset: usize,
+    reveal_key_hash: [u8; BLAKE2B_HASH_SIZE],
+    // Grace after voting_end: seconds on a timestamp schedule (timestamps
+    // are in ms), epochs on an epoch schedule. Only the matching one is set
+    submission_grace_seconds: u32,
+    submission_grace_epochs: u32,
+    signature_scheme: u8,
+    min_ballot_deposit: u64,
+    encryption_scheme: u8,
+    // Declared ballot payload length (0 = default BALLOT_PAYLOAD_SIZE)
+    ciphertext_len: u16,
+    bls_aggregate_pubkey: [u8; BLS_PUBKEY_SIZE],
+    revote_mode: u8,
+    ballot_format: u8,
+    // Registered candidates a ballot may select or rank (0 = no registry)
+    candidate_count: u8,
+    // Participants the fund must cover at ballot_fee each (0 = unchecked)
+    max_participants: u32,
+    // Minimum voters in each cohort that has any (0 = no cohort check)
+    cohort_k_threshold: u16,
+    // Key shares needed to rebuild the reveal key (0 = key revealed whole)
+    trustee_threshold: u8,
+    trustee_count: usize,
+    trustee_commitments: [[u8; SHARE_COMMITMENT_SIZE]; MAX_TRUSTEES],
+    // Window voters must register in before voting (0 = no registration)
+    registration_start: u64,
+    registration_end: u64,
+    // Key that may sweep the fund once recovery_unlock_time passes, should
+    // the organizer lose theirs (all zero = no recovery)
+    recovery_signer_hash: [u8; PUBKEY_HASH_SIZE],
+    recovery_unlock_time: u64,
+    // Full lock script hash of the organizer's own cells. organizer_lock_hash
+    // is their pubkey hash, so it can't identify a lock (all zero = unset)
+    organizer_lock_script_hash: [u8; BLAKE2B_HASH_SIZE],
+}
+
+/// Ballot counts established by tally verification
+///
+/// `ballots` and `abstentions` are in the same unit as the tallies: ballot
+/// weight for weighted events, one per ballot otherwise.
+struct TallyCounts {
+    ballots: u64,
+    abstentions: u64,
+    tally_count: usize,
+    tallies: [u32; MAX_VOTE_OPTIONS],
+}
+
+/// Offsets of the variable-length sections of a metadata cell
+struct MetadataLayout {
+    eligibility_offset: usize,
+    eligibility_len: usize,
+    signer_count: usize,
+    signers_offset: usize,
+    option_caps_offset: usize,
+    extensions_offset: usize,
+}
+
+/// Bounds-checked view over the data of a metadata cell
+///
+/// Section offsets are computed once on construction, so accessors never
+/// index into the cell with hand-written offsets.
+struct MetadataReader<'a> {
+    data: &'a [u8],
+    // None when the cell ends at the fixed core (no variable sections)
+    layout: Option<MetadataLayout>,
+}
+
+/// Script args decoded from any supported layout version
+struct ScriptArgs {
+    cell_type: u8,
+    event_id: [u8; EVENT_ID_SIZE],
+    // All zero when the args carry no voter
+    voter_hash: [u8; PUBKEY_HASH_SIZE],
+    has_voter_hash: bool,
+}
+
+/// The code a script runs, which VoteSecure locks share across cells
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ScriptCode {
+    code_hash: [u8; BLAKE2B_HASH_SIZE],
+    hash_type: u8,
+}
+
+/// Outcome of a cell load syscall
+///
+/// Scans over a source stop at `OutOfBound`; any other failure is a real
+/// error and must not be mistaken for the end of the cells.
+enum LoadResult {
+    Ok(usize),
+    OutOfBound,
+    ItemMissing,
+    Error,
+}
+
+impl LoadResult {
+    /// Classify a load syscall's return code
+    fn from_syscall(ret: u64, len: u64) -> Self {
+        match ret {
+            0 => LoadResult::Ok(len as usize),
+            SYSCALL_INDEX_OUT_OF_BOUND => LoadResult::OutOfBound,
+            SYSCALL_ITEM_MISSING => LoadResult::ItemMissing,
+            _ => LoadResult::Error,
+        }
+    }
+    
+    /// Convert to the form used by scan loops: `None` once past the last cell
+    fn scan(self) -> Result<Option<usize>, ContractError> {
+        match self {
+            LoadResult::Ok(len) => Ok(Some(len)),
+            LoadResult::OutOfBound => Ok(None),
+            LoadResult::ItemMissing | LoadResult::Error => Err(ContractError::Syscall),
+        }
+    }
 }
 
 // ============================================================================
@@ -173,6 +714,7 @@ struct ParsedMetadata {
 // ============================================================================
 
 /// Execute CKB syscall
+#[cfg(target_arch = "riscv64")]
 #[inline(always)]
 unsafe fn syscall(
     n: u64,
@@ -197,17 +739,135 @@ unsafe fn syscall(
     ret
 }
 
+/// Host unit tests answer syscalls from the transaction in `mock_chain`
+#[cfg(all(test, not(target_arch = "riscv64")))]
+unsafe fn syscall(
+    n: u64,
+    arg0: u64,
+    arg1: u64,
+    arg2: u64,
+    arg3: u64,
+    arg4: u64,
+    arg5: u64,
+) -> u64 {
+    mock_chain::syscall(n, arg0, arg1, arg2, arg3, arg4, arg5)
+}
+
+/// Host tooling builds have no CKB VM to call into
+#[cfg(all(not(test), not(target_arch = "riscv64")))]
+unsafe fn syscall(
+    n: u64,
+    _arg0: u64,
+    _arg1: u64,
+    _arg2: u64,
+    _arg3: u64,
+    _arg4: u64,
+    _arg5: u64,
+) -> u64 {
+    panic!("CKB syscall {} is only available on chain", n)
+}
+
 /// Exit with code
 #[inline(always)]
 fn exit(code: i8) -> ! {
     unsafe {
         syscall(SYS_EXIT, code as u64, 0, 0, 0, 0, 0);
     }
-    loop {}
+    // SYS_EXIT doesn't return
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+// ============================================================================
+// Cycle Instrumentation
+// ============================================================================
+
+// Validation phases measured by bench builds
+const BENCH_PHASE_SIGNATURES: usize = 0;
+const BENCH_PHASE_K_ANONYMITY: usize = 1;
+const BENCH_PHASE_TALLY: usize = 2;
+#[cfg(feature = "bench")]
+const BENCH_PHASE_COUNT: usize = 3;
+#[cfg(feature = "bench")]
+const BENCH_PHASE_NAMES: [&[u8]; BENCH_PHASE_COUNT] = [b"signatures", b"k_anonymity", b"tally"];
+
+#[cfg(feature = "bench")]
+static BENCH_CYCLES: [core::sync::atomic::AtomicU64; BENCH_PHASE_COUNT] = [
+    core::sync::atomic::AtomicU64::new(0),
+    core::sync::atomic::AtomicU64::new(0),
+    core::sync::atomic::AtomicU64::new(0),
+];
+
+/// Cycles consumed by the script so far
+#[cfg(feature = "bench")]
+fn current_cycles() -> u64 {
+    unsafe { syscall(SYS_CURRENT_CYCLES, 0, 0, 0, 0, 0, 0) }
+}
+
+/// Run a validation phase, charging the cycles it used to that phase
+///
+/// Without the `bench` feature this is just the call, so the hooks cost
+/// nothing on chain.
+#[inline(always)]
+fn bench_phase<T>(phase: usize, run: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "bench")]
+    let start = current_cycles();
+    
+    let result = run();
+    
+    #[cfg(feature = "bench")]
+    BENCH_CYCLES[phase].fetch_add(current_cycles() - start, core::sync::atomic::Ordering::Relaxed);
+    #[cfg(not(feature = "bench"))]
+    let _ = phase;
+    
+    result
+}
+
+/// Print the per-phase cycle counts
+///
+/// Each phase is written to the debug output as `bench <phase>: <cycles>`.
+/// The exit code is left alone, so a bench build accepts and rejects
+/// exactly what a release build does.
+#[cfg(feature = "bench")]
+fn bench_report() {
+    for (phase, name) in BENCH_PHASE_NAMES.iter().enumerate() {
+        let cycles = BENCH_CYCLES[phase].load(core::sync::atomic::Ordering::Relaxed);
+        
+        // "bench " + name + ": " + up to 20 digits + NUL
+        let mut line = [0u8; 64];
+        let mut len = 0;
+        for &b in b"bench ".iter().chain(name.iter()).chain(b": ".iter()) {
+            line[len] = b;
+            len += 1;
+        }
+        
+        let mut digits = [0u8; 20];
+        let mut digit_count = 0;
+        let mut rest = cycles;
+        loop {
+            digits[digit_count] = b'0' + (rest % 10) as u8;
+            digit_count += 1;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        for &d in digits[..digit_count].iter().rev() {
+            line[len] = d;
+            len += 1;
+        }
+        
+        unsafe {
+            syscall(SYS_DEBUG, line.as_ptr() as u64, 0, 0, 0, 0, 0);
+        }
+    }
 }
 
-/// Load script args
-fn load_script_args(buf: &mut [u8]) -> Result<usize, i8> {
+/// Load the running script as a molecule `Script`
+///
+/// Returns the full script length; see `parse_script` for the layout.
+fn load_script(buf: &mut [u8]) -> Result<usize, ContractError> {
     let mut len = buf.len() as u64;
     let ret = unsafe {
         syscall(
@@ -224,247 +884,729 @@ fn load_script_args(buf: &mut [u8]) -> Result<usize, i8> {
     if ret == 0 {
         Ok(len as usize)
     } else {
-        Err(ERROR_SYSCALL)
+        Err(ContractError::Syscall)
     }
 }
 
-/// Load cell data by field
-fn load_cell_by_field(
-    buf: &mut [u8],
-    index: usize,
-    source: u64,
-    field: u64,
-) -> Result<usize, i8> {
+/// Load the hash of the current transaction
+fn load_tx_hash(buf: &mut [u8; 32]) -> Result<usize, ContractError> {
     let mut len = buf.len() as u64;
     let ret = unsafe {
         syscall(
-            SYS_LOAD_CELL_BY_FIELD,
+            SYS_LOAD_TX_HASH,
             buf.as_mut_ptr() as u64,
             &mut len as *mut u64 as u64,
-            index as u64,
-            source,
-            field,
+            0,
+            0,
+            0,
             0,
         )
     };
     
-    if ret == 0 {
+    if ret == 0 && len as usize == BLAKE2B_HASH_SIZE {
         Ok(len as usize)
-    } else if ret == 2 {
-        // Index out of bound - no more cells
-        Err(ERROR_SYSCALL)
     } else {
-        Err(ERROR_SYSCALL)
+        Err(ContractError::Syscall)
     }
 }
 
-/// Load input cell by field
-fn load_input_by_field(
+/// Load cell data by field
+fn load_cell_by_field(
     buf: &mut [u8],
     index: usize,
+    source: u64,
     field: u64,
-) -> Result<usize, i8> {
-    load_cell_by_field(buf, index, SOURCE_INPUT, field)
+) -> Result<usize, ContractError> {
+    load_cell_by_field_at(buf, 0, index, source, field)
 }
 
-/// Load output cell by field
-fn load_output_by_field(
+/// Load cell data by field, starting `offset` bytes into the field
+///
+/// Only `buf.len()` bytes are copied, but the returned length is the full
+/// length of the field from `offset`, so callers can detect truncation.
+fn load_cell_by_field_at(
     buf: &mut [u8],
+    offset: usize,
     index: usize,
+    source: u64,
     field: u64,
-) -> Result<usize, i8> {
-    load_cell_by_field(buf, index, SOURCE_OUTPUT, field)
+) -> Result<usize, ContractError> {
+    match try_load_cell_by_field_at(buf, offset, index, source, field) {
+        LoadResult::Ok(len) => Ok(len),
+        _ => Err(ContractError::Syscall),
+    }
 }
 
-/// Load cell dep by field
-fn load_cell_dep_by_field(
+/// Load cell data by field, reporting exactly how the syscall failed
+fn try_load_cell_by_field_at(
     buf: &mut [u8],
+    offset: usize,
     index: usize,
+    source: u64,
     field: u64,
-) -> Result<usize, i8> {
-    load_cell_by_field(buf, index, SOURCE_CELL_DEP, field)
+) -> LoadResult {
+    let mut len = buf.len() as u64;
+    let ret = unsafe {
+        syscall(
+            SYS_LOAD_CELL_BY_FIELD,
+            buf.as_mut_ptr() as u64,
+            &mut len as *mut u64 as u64,
+            offset as u64,
+            index as u64,
+            source,
+            field,
+        )
+    };
+    
+    LoadResult::from_syscall(ret, len)
+}
+
+/// Load a cell's data, starting `offset` bytes in
+///
+/// Same length semantics as `load_cell_by_field_at`.
+fn load_cell_data_at(
+    buf: &mut [u8],
+    offset: usize,
+    index: usize,
+    source: u64,
+) -> Result<usize, ContractError> {
+    match try_load_cell_data_at(buf, offset, index, source) {
+        LoadResult::Ok(len) => Ok(len),
+        _ => Err(ContractError::Syscall),
+    }
+}
+
+/// Load a cell's data
+fn load_cell_data(buf: &mut [u8], index: usize, source: u64) -> Result<usize, ContractError> {
+    load_cell_data_at(buf, 0, index, source)
 }
 
-/// Load witness at index
-fn load_witness(buf: &mut [u8], index: usize) -> Result<usize, i8> {
+/// Load a cell's data, reporting exactly how the syscall failed
+fn try_load_cell_data_at(
+    buf: &mut [u8],
+    offset: usize,
+    index: usize,
+    source: u64,
+) -> LoadResult {
     let mut len = buf.len() as u64;
     let ret = unsafe {
         syscall(
-            SYS_LOAD_WITNESS,
+            SYS_LOAD_CELL_DATA,
             buf.as_mut_ptr() as u64,
             &mut len as *mut u64 as u64,
+            offset as u64,
             index as u64,
-            SOURCE_INPUT,
-            0,
+            source,
             0,
         )
     };
     
-    if ret == 0 {
-        Ok(len as usize)
-    } else {
-        Err(ERROR_SYSCALL)
+    LoadResult::from_syscall(ret, len)
+}
+
+/// Load a cell's data while scanning a source
+///
+/// Returns `Ok(None)` past the last cell, like `scan_cell_by_field`.
+fn scan_cell_data(buf: &mut [u8], index: usize, source: u64) -> Result<Option<usize>, ContractError> {
+    try_load_cell_data_at(buf, 0, index, source).scan()
+}
+
+/// Load a cell field while scanning a source
+///
+/// Returns `Ok(None)` past the last cell and an error for any other failure.
+fn scan_cell_by_field(
+    buf: &mut [u8],
+    index: usize,
+    source: u64,
+    field: u64,
+) -> Result<Option<usize>, ContractError> {
+    try_load_cell_by_field_at(buf, 0, index, source, field).scan()
+}
+
+/// Load only the identifying prefix of a cell's data
+///
+/// Fills `buf` with [type: 1][event_id: 32][voter_hash: 20] and returns the
+/// full data length, so type detection works for cells of any size. Bytes
+/// past the returned length are zeroed so stale data from a previous load
+/// can't match. Returns `Ok(None)` past the last cell of `source`.
+fn load_cell_prefix(
+    buf: &mut [u8; CELL_PREFIX_SIZE],
+    index: usize,
+    source: u64,
+) -> Result<Option<usize>, ContractError> {
+    let len = match scan_cell_data(buf, index, source)? {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+    
+    if len < CELL_PREFIX_SIZE {
+        for byte in buf[len..].iter_mut() {
+            *byte = 0;
+        }
     }
+    
+    Ok(Some(len))
 }
 
-/// Load block timestamp from header
-fn load_current_timestamp() -> Result<u64, i8> {
+/// Load the capacity (in shannons) of a cell
+fn load_capacity(index: usize, source: u64) -> Result<u64, ContractError> {
+    load_capacity_field(index, source, CELL_FIELD_CAPACITY)
+}
+
+/// Load the occupied capacity (in shannons) of a cell, the least it can hold
+fn load_occupied_capacity(index: usize, source: u64) -> Result<u64, ContractError> {
+    load_capacity_field(index, source, CELL_FIELD_OCCUPIED_CAPACITY)
+}
+
+/// Load a u64 capacity field of a cell
+fn load_capacity_field(index: usize, source: u64, field: u64) -> Result<u64, ContractError> {
     let mut buf = [0u8; 8];
+    let len = load_cell_by_field(&mut buf, index, source, field)?;
+    
+    if len != 8 {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Load raw witness bytes at index
+fn load_witness_raw(buf: &mut [u8], index: usize) -> Result<usize, ContractError> {
+    load_witness_raw_at(buf, 0, index)
+}
+
+/// Load part of the raw witness at index, starting `offset` bytes in
+///
+/// Like `load_cell_by_field_at`, returns the full length from `offset`.
+fn load_witness_raw_at(buf: &mut [u8], offset: usize, index: usize) -> Result<usize, ContractError> {
+    let mut len = buf.len() as u64;
     let ret = unsafe {
         syscall(
-            SYS_LOAD_HEADER_BY_FIELD,
+            SYS_LOAD_WITNESS,
             buf.as_mut_ptr() as u64,
-            &mut 8u64 as *mut u64 as u64,
-            0,
+            &mut len as *mut u64 as u64,
+            offset as u64,
+            index as u64,
             SOURCE_INPUT,
-            HEADER_FIELD_TIMESTAMP,
             0,
         )
     };
     
     if ret == 0 {
-        Ok(u64::from_le_bytes(buf))
+        Ok(len as usize)
     } else {
-        Err(ERROR_SYSCALL)
+        Err(ContractError::Syscall)
     }
 }
 
-// ============================================================================
-// Cryptographic Functions
-// ============================================================================
-
-/// Blake2b hash function (simplified for CKB)
-fn blake2b_hash(data: &[u8], output: &mut [u8; BLAKE2B_HASH_SIZE]) {
-    // In production CKB environment, use ckb-std's blake2b
-    // For now, this is a placeholder that copies/pads the data
-    // In real implementation, call CKB's blake2b syscall or use ckb-std crate
-    
-    let len = core::cmp::min(data.len(), BLAKE2B_HASH_SIZE);
-    output[..len].copy_from_slice(&data[..len]);
-    
-    // Zero-fill remaining bytes if data is shorter
-    if len < BLAKE2B_HASH_SIZE {
-        for i in len..BLAKE2B_HASH_SIZE {
-            output[i] = 0;
-        }
+/// Load exactly `buf.len()` witness bytes starting `offset` bytes in
+///
+/// Reads one window of a large witness in place. Fails rather than leaving
+/// the tail of `buf` stale when the witness ends before the window does.
+fn load_witness_range(buf: &mut [u8], offset: usize, index: usize) -> Result<(), ContractError> {
+    let available = load_witness_raw_at(buf, offset, index)?;
+    if available < buf.len() {
+        return Err(ContractError::Encoding);
     }
+    
+    Ok(())
 }
 
-/// Verify secp256k1 signature
-/// 
-/// This implements production-ready ECDSA signature verification using secp256k1.
-/// It validates that the signature was created by the private key corresponding
-/// to the provided public key, over the given message hash.
-/// 
-/// Parameters:
-/// - pubkey: 33-byte compressed secp256k1 public key
-/// - signature: 64-byte signature (r || s)
-/// - message_hash: 32-byte hash of the message being signed
-/// 
-/// Returns: true if signature is valid, false otherwise
-fn verify_secp256k1_signature(
-    pubkey: &[u8],
-    signature: &[u8],
-    message_hash: &[u8; 32],
-) -> bool {
-    // Validate input lengths
-    if pubkey.len() != PUBKEY_SIZE {
-        return false;
+/// Load the `lock` field of the WitnessArgs at index
+///
+/// Wallets encode witnesses as a molecule `WitnessArgs` table, with the
+/// signature material in `lock`. The lock bytes are moved to the start of
+/// `buf` and their length returned.
+fn load_witness_lock(buf: &mut [u8], index: usize) -> Result<usize, ContractError> {
+    let witness_len = load_witness_raw(buf, index)?;
+    if witness_len == 0 {
+        return Err(ContractError::EmptyWitness);
     }
-    if signature.len() != SIGNATURE_SIZE {
-        return false;
+    if witness_len > buf.len() {
+        return Err(ContractError::Encoding);
     }
     
-    // Check that signature is not all zeros (invalid)
-    let mut has_nonzero = false;
-    for &byte in signature {
-        if byte != 0 {
-            has_nonzero = true;
-            break;
-        }
+    let (lock_start, lock_len) = parse_witness_args_lock(&buf[..witness_len], witness_len)?;
+    // A WitnessArgs without lock bytes carries no signature either
+    if lock_len == 0 {
+        return Err(ContractError::EmptyWitness);
     }
-    if !has_nonzero {
-        return false;
+    buf.copy_within(lock_start..lock_start + lock_len, 0);
+    
+    Ok(lock_len)
+}
+
+/// Locate the `lock` field of the WitnessArgs at index without loading it
+///
+/// Only the WitnessArgs header is read; the lock bytes can then be loaded
+/// piecewise with `load_witness_raw_at`, so their size isn't bounded by a
+/// buffer.
+fn locate_witness_lock(index: usize) -> Result<(usize, usize), ContractError> {
+    let mut header = [0u8; WITNESS_ARGS_HEADER_SIZE + 4];
+    let witness_len = load_witness_raw(&mut header, index)?;
+    if witness_len == 0 {
+        return Err(ContractError::EmptyWitness);
     }
+    let loaded = core::cmp::min(witness_len, header.len());
     
-    // In production: Use CKB's secp256k1 verification
-    // This would typically call a syscall or use ckb-std's verify_signature
-    // 
-    // The actual implementation would be:
-    // 1. Parse the compressed public key
-    // 2. Parse the signature (r, s values)
-    // 3. Verify the signature using secp256k1_ecdsa_verify
-    //
-    // For CKB, you would use:
-    // - ckb_crypto::secp256k1::Pubkey::from_slice(pubkey)
-    // - ckb_crypto::secp256k1::Signature::from_compact(signature)
-    // - verify using the secp256k1 context
-    
-    // Placeholder implementation for compilation:
-    // In real production, replace this with actual secp256k1 verification
-    // using CKB's crypto libraries or syscalls
-    
-    // Basic sanity checks as a starting point:
-    // 1. First byte of compressed pubkey should be 0x02 or 0x03
-    if pubkey[0] != 0x02 && pubkey[0] != 0x03 {
-        return false;
+    parse_witness_args_lock(&header[..loaded], witness_len)
+}
+
+/// Locate the `lock` bytes inside a molecule-encoded WitnessArgs
+///
+/// Layout: [total_size: u32][offsets: u32 x 3][lock: BytesOpt]
+/// [input_type: BytesOpt][output_type: BytesOpt], where a present BytesOpt
+/// is [len: u32][bytes]. `witness` must hold at least the header and lock
+/// length; `witness_len` is the full witness size. Returns (start, len); an
+/// absent lock has len 0. Pure, like `parse_metadata`.
+fn parse_witness_args_lock(witness: &[u8], witness_len: usize) -> Result<(usize, usize), ContractError> {
+    let read_u32 = |offset: usize| -> usize {
+        u32::from_le_bytes([
+            witness[offset], witness[offset + 1],
+            witness[offset + 2], witness[offset + 3],
+        ]) as usize
+    };
+    
+    if witness.len() < WITNESS_ARGS_HEADER_SIZE {
+        return Err(ContractError::Encoding);
     }
     
-    // 2. Signature values should not be zero or exceed curve order
-    // For secp256k1, the order n is:
-    // 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+    let total_size = read_u32(0);
+    let lock_offset = read_u32(4);
+    let input_type_offset = read_u32(8);
     
-    // Check r value (first 32 bytes) is not zero
-    let mut r_is_zero = true;
-    for i in 0..32 {
-        if signature[i] != 0 {
-            r_is_zero = false;
-            break;
-        }
+    if total_size != witness_len
+        || lock_offset != WITNESS_ARGS_HEADER_SIZE
+        || input_type_offset < lock_offset
+        || input_type_offset > total_size
+    {
+        return Err(ContractError::Encoding);
     }
-    if r_is_zero {
-        return false;
+    
+    // Absent lock
+    if input_type_offset == lock_offset {
+        return Ok((lock_offset, 0));
     }
     
-    // Check s value (last 32 bytes) is not zero
-    let mut s_is_zero = true;
-    for i in 32..64 {
-        if signature[i] != 0 {
-            s_is_zero = false;
-            break;
-        }
+    if input_type_offset - lock_offset < 4 || witness.len() < lock_offset + 4 {
+        return Err(ContractError::Encoding);
     }
-    if s_is_zero {
-        return false;
+    
+    let lock_len = read_u32(lock_offset);
+    if lock_offset + 4 + lock_len != input_type_offset {
+        return Err(ContractError::Encoding);
     }
     
-    // TODO: PRODUCTION DEPLOYMENT REQUIREMENT
-    // Before deploying to mainnet, replace this section with one of:
-    // 
-    // Option 1: Use ckb-std (recommended)
-    // extern crate ckb_std;
-    // use ckb_std::ckb_crypto::secp256k1;
-    // 
-    // let pubkey_obj = match secp256k1::Pubkey::from_slice(pubkey) {
-    //     Ok(pk) => pk,
-    //     Err(_) => return false,
-    // };
+    Ok((lock_offset + 4, lock_len))
+}
+
+/// Map a witness loading failure to the caller's authorization error
+///
+/// An empty witness keeps its own error so a missing signature isn't
+/// reported as a wrong one.
+fn witness_error(e: ContractError, fallback: ContractError) -> ContractError {
+    match e {
+        ContractError::EmptyWitness => ContractError::EmptyWitness,
+        _ => fallback,
+    }
+}
+
+/// Map a rejected signature to the caller's authorization error
+///
+/// A signature from the wrong key keeps its own error, so a client that
+/// signed with another account can tell that apart from a corrupt one.
+fn signature_error(failure: SignatureFailure, fallback: ContractError) -> ContractError {
+    match failure {
+        SignatureFailure::HashMismatch => ContractError::SignerMismatch,
+        SignatureFailure::BadSignature => fallback,
+    }
+}
+
+/// Load block timestamp from header
+///
+/// The time is that of the block which created the guarded cell, group
+/// input 0, whatever its index among the transaction's inputs. Its header
+/// must be attached as a header dep, or this fails with `HeaderMissing`.
+/// The transaction builder can't pick a later block for it, and the
+/// transaction commits after it, so the value is a lower bound on when the
+/// transaction commits. A cell created during voting can still be spent
+/// after voting closes, which is why the voting deadline is also checked
+/// against each ballot's own creation block at release.
+///
+/// A cell created long ago would keep the clock at its creation, so the
+/// input may move it forward with an absolute timestamp `since`: consensus
+/// refuses to commit the transaction before that median time, making it a
+/// lower bound as well. A later header dep never moves the clock.
+fn load_current_timestamp() -> Result<u64, ContractError> {
+    let created = load_group_header_field(HEADER_FIELD_TIMESTAMP)?;
+    
+    // Since timestamps count seconds, headers milliseconds
+    match load_group_since(SINCE_METRIC_TIMESTAMP)? {
+        Some(seconds) => Ok(created.max(seconds.saturating_mul(1000))),
+        None => Ok(created),
+    }
+}
+
+/// Load the epoch number on the same terms as `load_current_timestamp`
+fn load_current_epoch() -> Result<u64, ContractError> {
+    let created = load_group_header_field(HEADER_FIELD_EPOCH_NUMBER)?;
+    
+    match load_group_since(SINCE_METRIC_EPOCH)? {
+        Some(epoch) => Ok(created.max(epoch & SINCE_EPOCH_NUMBER_MASK)),
+        None => Ok(created),
+    }
+}
+
+/// Load a u64 header field of the block that created group input 0
+fn load_group_header_field(field: u64) -> Result<u64, ContractError> {
+    load_header_field(0, SOURCE_GROUP_INPUT, field)?.ok_or(ContractError::HeaderMissing)
+}
+
+/// Load the value of group input 0's since if it is absolute in `metric`
+fn load_group_since(metric: u64) -> Result<Option<u64>, ContractError> {
+    let mut buf = [0u8; 8];
+    let mut len = buf.len() as u64;
+    let ret = unsafe {
+        syscall(
+            SYS_LOAD_INPUT_BY_FIELD,
+            buf.as_mut_ptr() as u64,
+            &mut len as *mut u64 as u64,
+            0,
+            0,
+            SOURCE_GROUP_INPUT,
+            INPUT_FIELD_SINCE,
+        )
+    };
+    if ret != 0 || len != 8 {
+        return Err(ContractError::Syscall);
+    }
+    
+    let since = u64::from_le_bytes(buf);
+    if since & (SINCE_RELATIVE_FLAG | SINCE_METRIC_MASK) != metric {
+        return Ok(None);
+    }
+    
+    Ok(Some(since & SINCE_VALUE_MASK))
+}
+
+/// Load the schedule time of the block that created a cell
+///
+/// Timestamp or epoch number depending on the event's schedule basis. The
+/// block's header must be attached as a header dep.
+fn load_cell_schedule_time(metadata: &ParsedMetadata, index: usize, source: u64) -> Result<u64, ContractError> {
+    let field = if metadata.flags & FLAG_EPOCH_SCHEDULE != 0 {
+        HEADER_FIELD_EPOCH_NUMBER
+    } else {
+        HEADER_FIELD_TIMESTAMP
+    };
+    
+    load_header_field(index, source, field)?.ok_or(ContractError::HeaderMissing)
+}
+
+/// Load a u64 header field for a header dep, or for the block of a cell
+///
+/// Returns `Ok(None)` past the last item of `source`. A cell whose block
+/// is not among the header deps fails with `HeaderMissing`.
+fn load_header_field(index: usize, source: u64, field: u64) -> Result<Option<u64>, ContractError> {
+    let mut buf = [0u8; 8];
+    let mut len = buf.len() as u64;
+    let ret = unsafe {
+        syscall(
+            SYS_LOAD_HEADER_BY_FIELD,
+            buf.as_mut_ptr() as u64,
+            &mut len as *mut u64 as u64,
+            0,
+            index as u64,
+            source,
+            field,
+        )
+    };
+    
+    match ret {
+        0 => Ok(Some(u64::from_le_bytes(buf))),
+        SYSCALL_INDEX_OUT_OF_BOUND => Ok(None),
+        SYSCALL_ITEM_MISSING => Err(ContractError::HeaderMissing),
+        _ => Err(ContractError::Syscall),
+    }
+}
+
+// ============================================================================
+// Cryptographic Functions
+// ============================================================================
+
+/// Blake2b-256 with CKB's "ckb-default-hash" personalization
+fn blake2b_hash(data: &[u8], output: &mut [u8; BLAKE2B_HASH_SIZE]) {
+    blake2b::blake2b_256(data, output);
+}
+
+/// Verify secp256k1 signature
+/// 
+/// Checks an ECDSA signature made by the private key behind `pubkey` over
+/// `message_hash`, which is signed as-is (no further hashing).
+/// 
+/// Parameters:
+/// - pubkey: 33-byte compressed secp256k1 public key
+/// - signature: 64-byte signature (r || s)
+/// - message_hash: 32-byte hash of the message being signed
+/// 
+/// Returns: true if signature is valid, false otherwise
+fn verify_secp256k1_signature(
+    pubkey: &[u8],
+    signature: &[u8],
+    message_hash: &[u8; 32],
+) -> bool {
+    // Validate input lengths
+    if pubkey.len() != PUBKEY_SIZE {
+        return false;
+    }
+    if signature.len() != SIGNATURE_SIZE {
+        return false;
+    }
+    
+    // Only compressed keys are accepted, so each signer has one lock hash
+    if pubkey[0] != 0x02 && pubkey[0] != 0x03 {
+        return false;
+    }
+    
+    // s must be canonical (low-s), otherwise (r, n - s) would be a second
+    // valid signature for the same message
+    let mut s_value = [0u8; 32];
+    s_value.copy_from_slice(&signature[32..64]);
+    if !is_low_s(&s_value) {
+        return false;
+    }
+    
+    let verifying_key = match VerifyingKey::from_sec1_bytes(pubkey) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    
+    // Rejects r or s of zero or outside the curve order
+    let signature = match Signature::from_slice(signature) {
+        Ok(sig) => sig,
+        Err(_) => return false,
+    };
+    
+    verifying_key.verify_prehash(message_hash, &signature).is_ok()
+}
+
+/// Check that a big-endian ECDSA s value is in the lower half of the order
+fn is_low_s(s: &[u8; 32]) -> bool {
+    for i in 0..32 {
+        if s[i] != SECP256K1_HALF_ORDER[i] {
+            return s[i] < SECP256K1_HALF_ORDER[i];
+        }
+    }
+    
+    // s == n / 2 is still low
+    true
+}
+
+/// Verify a BIP340 Schnorr signature
+///
+/// Parameters:
+/// - pubkey_xonly: 32-byte x-only public key
+/// - signature: 64-byte signature (R.x || s)
+/// - message_hash: 32-byte hash of the message being signed
+///
+/// Returns: true if signature is valid, false otherwise
+fn verify_schnorr_signature(
+    pubkey_xonly: &[u8],
+    signature: &[u8],
+    message_hash: &[u8; 32],
+) -> bool {
+    if pubkey_xonly.len() != XONLY_PUBKEY_SIZE {
+        return false;
+    }
+    if signature.len() != SIGNATURE_SIZE {
+        return false;
+    }
+    
+    // An all-zero key is not a point on the curve
+    if pubkey_xonly.iter().all(|&b| b == 0) {
+        return false;
+    }
+    
+    // Neither R.x (first 32 bytes) nor s (last 32 bytes) may be zero
+    if signature[..32].iter().all(|&b| b == 0) || signature[32..].iter().all(|&b| b == 0) {
+        return false;
+    }
+    
+    // TODO: PRODUCTION DEPLOYMENT REQUIREMENT
+    // As with verify_secp256k1_signature, replace this with real BIP340
+    // verification (e.g. ckb-std's secp256k1 schnorr support):
     // 
-    // let sig_obj = match secp256k1::Signature::from_compact(signature) {
-    //     Ok(sig) => sig,
-    //     Err(_) => return false,
-    // };
+    // let pubkey_obj = XOnlyPublicKey::from_slice(pubkey_xonly)?;
+    // let sig_obj = schnorr::Signature::from_slice(signature)?;
+    // let message = Message::from_slice(message_hash)?;
+    // secp.verify_schnorr(&sig_obj, &message, &pubkey_obj).is_ok()
+    let _ = message_hash;
+    PLACEHOLDER_VERIFIERS
+}
+
+/// Verify a BLS12-381 aggregate signature
+///
+/// The aggregate public key is the sum of the invite issuers' keys, so a
+/// valid aggregate shows the message was approved without identifying
+/// which individual invite was used.
+///
+/// Parameters:
+/// - agg_pubkey: 48-byte compressed G1 aggregate public key
+/// - agg_sig: 96-byte compressed G2 aggregate signature
+/// - message: 32-byte message every invite approval signed
+///
+/// Returns: true if signature is valid, false otherwise
+fn verify_bls_aggregate(
+    agg_pubkey: &[u8],
+    agg_sig: &[u8],
+    message: &[u8; 32],
+) -> bool {
+    if agg_pubkey.len() != BLS_PUBKEY_SIZE {
+        return false;
+    }
+    if agg_sig.len() != BLS_SIGNATURE_SIZE {
+        return false;
+    }
+    
+    // Compressed points must have the compression bit (0x80) set; the
+    // infinity bit (0x40) marks the identity, which proves nothing
+    if agg_pubkey[0] & 0x80 == 0 || agg_pubkey[0] & 0x40 != 0 {
+        return false;
+    }
+    if agg_sig[0] & 0x80 == 0 || agg_sig[0] & 0x40 != 0 {
+        return false;
+    }
+    
+    // TODO: PRODUCTION DEPLOYMENT REQUIREMENT
+    // As with verify_secp256k1_signature, replace this with a real pairing
+    // check e(agg_pubkey, H(message)) == e(G1, agg_sig), including subgroup
+    // checks on both points (e.g. via a BLS verifier cell dep):
     // 
-    // let message = secp256k1::Message::from_slice(message_hash).unwrap();
-    // sig_obj.verify(&message, &pubkey_obj).is_ok()
-    //
-    // Option 2: Direct syscall to CKB's crypto library
-    // Call SYS_LOAD_CELL_DATA with proper parameters to access
-    // the secp256k1 verification functions
-    
-    // For now, return true after basic validation for testnet development
-    // CRITICAL: This must be replaced before production use!
-    true
+    // let pk = PublicKey::from_bytes(agg_pubkey)?;
+    // let sig = Signature::from_bytes(agg_sig)?;
+    // sig.verify(true, message, BLS_DST, &[], &pk, true) == BLST_ERROR::BLST_SUCCESS
+    let _ = message;
+    PLACEHOLDER_VERIFIERS
+}
+
+/// Verify a proof that a committed ranking is well formed
+///
+/// The statement proven is that the ranking opening `commitment` lists
+/// `rank_count` candidates, each below `candidate_count`, with no candidate
+/// ranked twice. The ballot stays encrypted; only the proof is checked.
+///
+/// Returns: true if the proof is valid, false otherwise
+fn verify_ranking_proof(
+    commitment: &[u8],
+    rank_count: u8,
+    candidate_count: u8,
+    proof: &[u8],
+) -> bool {
+    if commitment.len() != BALLOT_COMMITMENT_SIZE || proof.len() != RANKING_PROOF_SIZE {
+        return false;
+    }
+    if rank_count == 0 || rank_count > candidate_count {
+        return false;
+    }
+    
+    // An all-zero proof is never valid
+    if proof.iter().all(|&b| b == 0) {
+        return false;
+    }
+    
+    // TODO: PRODUCTION DEPLOYMENT REQUIREMENT
+    // As with verify_secp256k1_signature, replace this with the real
+    // verifier for the ranking circuit (distinct, in-range ranks under the
+    // commitment), e.g. a Groth16 check via a verifier cell dep.
+    PLACEHOLDER_VERIFIERS
+}
+
+/// Verify a proof that a decryption share belongs to a trustee
+///
+/// The statement proven (a DLEQ proof) is that `share` was derived from the
+/// secret behind the share pubkey `commitment` published in metadata, so a
+/// trustee can't submit a share that doesn't match their committed key.
+///
+/// Returns: true if the proof is valid, false otherwise
+fn verify_share_proof(
+    commitment: &[u8],
+    share: &[u8],
+    proof: &[u8],
+) -> bool {
+    if commitment.len() != SHARE_COMMITMENT_SIZE || share.len() != REVEAL_KEY_SIZE || proof.len() != SHARE_PROOF_SIZE {
+        return false;
+    }
+    
+    // An all-zero share or proof is never valid
+    if share.iter().all(|&b| b == 0) || proof.iter().all(|&b| b == 0) {
+        return false;
+    }
+    
+    // TODO: PRODUCTION DEPLOYMENT REQUIREMENT
+    // As with verify_secp256k1_signature, replace this with the real DLEQ
+    // check of the share against the trustee's committed share pubkey.
+    PLACEHOLDER_VERIFIERS
+}
+
+/// Multiply two elements of GF(2^8) (AES polynomial x^8 + x^4 + x^3 + x + 1)
+fn gf256_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1B;
+        }
+        b >>= 1;
+    }
+    
+    product
+}
+
+/// Invert a nonzero element of GF(2^8) as a^254
+fn gf256_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    
+    while exp != 0 {
+        if exp & 1 != 0 {
+            result = gf256_mul(result, base);
+        }
+        base = gf256_mul(base, base);
+        exp >>= 1;
+    }
+    
+    result
+}
+
+/// Rebuild a secret from Shamir shares over GF(2^8), byte by byte
+///
+/// `xs` are the distinct, nonzero share points and `shares` the matching
+/// share values; the secret is the polynomial's value at x = 0.
+fn combine_shares(xs: &[u8], shares: &[[u8; REVEAL_KEY_SIZE]]) -> [u8; REVEAL_KEY_SIZE] {
+    let mut secret = [0u8; REVEAL_KEY_SIZE];
+    
+    for (i, &xi) in xs.iter().enumerate() {
+        // Lagrange basis at zero: product of xj / (xj - xi), where
+        // subtraction in GF(2^8) is xor
+        let mut basis = 1u8;
+        for (j, &xj) in xs.iter().enumerate() {
+            if i != j {
+                basis = gf256_mul(basis, gf256_mul(xj, gf256_inv(xj ^ xi)));
+            }
+        }
+        
+        for (byte, &share_byte) in secret.iter_mut().zip(shares[i].iter()) {
+            *byte ^= gf256_mul(share_byte, basis);
+        }
+    }
+    
+    secret
 }
 
 /// Compute pubkey hash from full public key
@@ -479,16 +1621,25 @@ fn compute_pubkey_hash(pubkey: &[u8; PUBKEY_SIZE]) -> [u8; PUBKEY_HASH_SIZE] {
     result
 }
 
+/// Why a signature was rejected by `verify_signature_by_hash`
+#[derive(Clone, Copy)]
+enum SignatureFailure {
+    // A valid-looking entry from a key other than the expected signer
+    HashMismatch,
+    // The entry is malformed or its signature doesn't verify
+    BadSignature,
+}
+
 /// Verify a signature against an expected pubkey hash
 /// This is the standard pattern for CKB lock scripts
 fn verify_signature_by_hash(
     expected_hash: &[u8; PUBKEY_HASH_SIZE],
     witness_data: &[u8],
     message_hash: &[u8; 32],
-) -> bool {
+) -> Result<(), SignatureFailure> {
     // Parse witness format: [pubkey: 33 bytes][signature: 64 bytes]
     if witness_data.len() < PUBKEY_SIZE + SIGNATURE_SIZE {
-        return false;
+        return Err(SignatureFailure::BadSignature);
     }
     
     let pubkey = &witness_data[0..PUBKEY_SIZE];
@@ -497,16 +1648,20 @@ fn verify_signature_by_hash(
     // Verify the pubkey hash matches
     let pubkey_array: [u8; PUBKEY_SIZE] = match pubkey.try_into() {
         Ok(arr) => arr,
-        Err(_) => return false,
+        Err(_) => return Err(SignatureFailure::BadSignature),
     };
     let computed_hash = compute_pubkey_hash(&pubkey_array);
     
-    if computed_hash != *expected_hash {
-        return false;
+    if !bytes_equal_ct(&computed_hash, expected_hash) {
+        return Err(SignatureFailure::HashMismatch);
     }
     
     // Verify the signature
-    verify_secp256k1_signature(pubkey, signature, message_hash)
+    if !verify_secp256k1_signature(pubkey, signature, message_hash) {
+        return Err(SignatureFailure::BadSignature);
+    }
+    
+    Ok(())
 }
 
 // ============================================================================
@@ -514,6 +1669,9 @@ fn verify_signature_by_hash(
 // ============================================================================
 
 /// Compare two byte slices for equality
+///
+/// Short-circuits on the first difference; only use for public values such
+/// as event ids. Use `bytes_equal_ct` for hashes and signatures.
 fn bytes_equal(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
@@ -528,600 +1686,5421 @@ fn bytes_equal(a: &[u8], b: &[u8]) -> bool {
     true
 }
 
+/// Compare two byte slices for equality in constant time
+///
+/// Always scans the full length and accumulates differences, so the time
+/// taken doesn't reveal where the first mismatching byte is.
+fn bytes_equal_ct(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    
+    let mut diff = 0u8;
+    for i in 0..a.len() {
+        diff |= a[i] ^ b[i];
+    }
+    
+    diff == 0
+}
+
+/// Overwrite a buffer holding sensitive data with zeros
+///
+/// Volatile writes keep the compiler from eliding stores to a buffer that
+/// is never read again.
+fn secure_zero(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        unsafe { core::ptr::write_volatile(byte, 0) };
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
 /// Find metadata cell in cell deps
-fn find_metadata_cell(event_id: &[u8]) -> Result<usize, i8> {
-    let mut buf = [0u8; 512];
+fn find_metadata_cell(event_id: &[u8]) -> Result<usize, ContractError> {
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    let mut found = None;
     
-    for i in 0..16 {
-        if load_cell_dep_by_field(&mut buf, i, CELL_FIELD_DATA).is_err() {
-            break;
-        }
+    // Scan every dep: a forged metadata cell supplied alongside the real
+    // one must make the lookup fail rather than depend on dep order. There
+    // is no fixed bound; the scan ends at the first out-of-bound index.
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_CELL_DEP)? {
+            Some(len) => len,
+            None => break,
+        };
         
         // Check if this is a metadata cell for our event
-        if buf[0] == METADATA_TYPE && bytes_equal(&buf[1..33], event_id) {
-            return Ok(i);
+        if len >= 33 && cell_type_of(buf[0]) == METADATA_TYPE && bytes_equal(&buf[1..33], event_id) {
+            if found.is_some() {
+                return Err(ContractError::AmbiguousMetadata);
+            }
+            found = Some(i);
         }
     }
     
-    Err(ERROR_METADATA_NOT_FOUND)
+    found.ok_or(ContractError::MetadataNotFound)
+}
+
+/// Load an event's metadata cell data from the cell deps
+///
+/// Returns the data length, failing with `Encoding` when the cell exceeds
+/// MAX_METADATA_SIZE rather than handing back a truncated copy.
+fn load_metadata_bytes(event_id: &[u8], buf: &mut [u8; MAX_METADATA_SIZE]) -> Result<usize, ContractError> {
+    let metadata_index = find_metadata_cell(event_id)?;
+    let len = load_cell_data(buf, metadata_index, SOURCE_CELL_DEP)?;
+    if len > MAX_METADATA_SIZE {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(len)
 }
 
 /// Load and parse metadata for an event
-fn load_metadata(event_id: &[u8]) -> Result<ParsedMetadata, i8> {
-    let mut buf = [0u8; 512];
+fn load_metadata(event_id: &[u8]) -> Result<ParsedMetadata, ContractError> {
+    let mut buf = [0u8; MAX_METADATA_SIZE];
+    let len = load_metadata_bytes(event_id, &mut buf)?;
     
-    let metadata_index = find_metadata_cell(event_id)?;
-    load_cell_dep_by_field(&mut buf, metadata_index, CELL_FIELD_DATA)?;
+    let metadata = parse_metadata(&buf[..len])?;
+    
+    // The cell was located by its raw event id bytes; confirm the parsed
+    // layout agrees, so a drifted reader can't validate another event
+    if metadata.event_id[..] != event_id[..] {
+        return Err(ContractError::MetadataNotFound);
+    }
     
+    Ok(metadata)
+}
+
+/// Parse and validate metadata cell data
+///
+/// Pure: works on the bytes alone and makes no syscalls, so it can be run
+/// off-chain over arbitrary input. Loaders check for truncation first.
+fn parse_metadata(data: &[u8]) -> Result<ParsedMetadata, ContractError> {
     // Parse metadata structure
     // Format: [type: 1][event_id: 32][organizer_lock_hash: 20][voting_start: 8][voting_end: 8][audit_end: 8][mode: 1][revotes: 1][sigs: 1][k: 2]
-    if buf.len() < 82 {
-        return Err(ERROR_ENCODING);
-    }
+    let reader = MetadataReader::new(data, data.len())?;
     
     let mut event_id_arr = [0u8; EVENT_ID_SIZE];
-    event_id_arr.copy_from_slice(&buf[1..33]);
+    event_id_arr.copy_from_slice(reader.event_id());
     
     let mut organizer_hash = [0u8; PUBKEY_HASH_SIZE];
-    organizer_hash.copy_from_slice(&buf[33..53]);
+    organizer_hash.copy_from_slice(reader.organizer_lock_hash());
     
-    let voting_start = u64::from_le_bytes([
-        buf[53], buf[54], buf[55], buf[56],
-        buf[57], buf[58], buf[59], buf[60],
-    ]);
+    let mut metadata = ParsedMetadata {
+        event_id: event_id_arr,
+        organizer_lock_hash: organizer_hash,
+        voting_start: reader.voting_start(),
+        voting_end: reader.voting_end(),
+        audit_end_time: reader.audit_end_time(),
+        eligibility_mode: reader.eligibility_mode(),
+        max_revotes: reader.max_revotes(),
+        required_signatures: reader.required_signatures(),
+        k_anonymity_threshold: reader.k_anonymity_threshold(),
+        frontend_code_hash: [0u8; BLAKE2B_HASH_SIZE],
+        flags: 0,
+        eligible_voter_count: 0,
+        invite_key_hash: [0u8; PUBKEY_HASH_SIZE],
+        quorum: 0,
+        ballot_fee: 0,
+        breakglass_hash: [0u8; PUBKEY_HASH_SIZE],
+        flags_offset: 0,
+        signer_count: 0,
+        token_type_hash: [0u8; 32],
+        paused: 0,
+        paused_offset: 0,
+        reveal_key_hash: [0u8; BLAKE2B_HASH_SIZE],
+        submission_grace_seconds: 0,
+        submission_grace_epochs: 0,
+        signature_scheme: SIGNATURE_SCHEME_ECDSA,
+        min_ballot_deposit: 0,
+        encryption_scheme: ENCRYPTION_SCHEME_DEFAULT,
+        ciphertext_len: 0,
+        bls_aggregate_pubkey: [0u8; BLS_PUBKEY_SIZE],
+        revote_mode: REVOTE_MODE_APPEND,
+        ballot_format: BALLOT_FORMAT_SINGLE,
+        candidate_count: 0,
+        max_participants: 0,
+        cohort_k_threshold: 0,
+        trustee_threshold: 0,
+        trustee_count: 0,
+        trustee_commitments: [[0u8; SHARE_COMMITMENT_SIZE]; MAX_TRUSTEES],
+        registration_start: 0,
+        registration_end: 0,
+        recovery_signer_hash: [0u8; PUBKEY_HASH_SIZE],
+        recovery_unlock_time: 0,
+        organizer_lock_script_hash: [0u8; BLAKE2B_HASH_SIZE],
+    };
     
-    let voting_end = u64::from_le_bytes([
-        buf[61], buf[62], buf[63], buf[64],
-        buf[65], buf[66], buf[67], buf[68],
-    ]);
+    if let Some(frontend_code_hash) = reader.frontend_code_hash() {
+        metadata.frontend_code_hash.copy_from_slice(frontend_code_hash);
+    }
+    metadata.signer_count = reader.signer_count();
     
-    let audit_end_time = u64::from_le_bytes([
-        buf[69], buf[70], buf[71], buf[72],
-        buf[73], buf[74], buf[75], buf[76],
-    ]);
+    parse_metadata_extensions(&reader, &mut metadata)?;
+    validate_schedule(&metadata)?;
     
-    let eligibility_mode = buf[77];
-    let max_revotes = buf[78];
-    let required_signatures = buf[79];
-    let k_anonymity_threshold = u16::from_le_bytes([buf[80], buf[81]]);
+    // Skipping the audit window is judged by the eligible voter count, so
+    // it must be the curated list's own length rather than a bare claim:
+    // in any other mode nothing bounds who votes
+    if metadata.flags & FLAG_INSTANT_FINALIZE != 0
+        && metadata.audit_end_time == metadata.voting_end
+        && (metadata.eligibility_mode != ELIGIBILITY_CURATED_LIST
+            || reader.eligibility_data().len() != metadata.eligible_voter_count as usize * CURATED_LEAF_SIZE)
+    {
+        return Err(ContractError::InvalidTiming);
+    }
     
-    Ok(ParsedMetadata {
-        event_id: event_id_arr,
-        organizer_lock_hash: organizer_hash,
-        voting_start,
-        voting_end,
-        audit_end_time,
-        eligibility_mode,
-        max_revotes,
-        required_signatures,
-        k_anonymity_threshold,
-    })
+    // BLS aggregate verification is still a placeholder
+    if metadata.eligibility_mode == ELIGIBILITY_INVITE_BLS && !PLACEHOLDER_VERIFIERS {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    // A declared ciphertext must leave room for the ranking proof
+    if metadata.ballot_format == BALLOT_FORMAT_RANKED
+        && metadata.ciphertext_len != 0
+        && (metadata.ciphertext_len as usize) < MIN_RANKED_CIPHERTEXT_LEN
+    {
+        return Err(ContractError::Encoding);
+    }
+    
+    // Pinning to an unset frontend hash would accept any dep with empty data
+    if metadata.flags & FLAG_PIN_FRONTEND != 0 && metadata.frontend_code_hash == [0u8; BLAKE2B_HASH_SIZE] {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    // Metadata without a signer list has no signers
+    if metadata.required_signatures as usize > metadata.signer_count {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    // K-anonymity is a privacy guarantee, so a public event setting k is
+    // misconfigured: the organizer must explicitly opt in to privacy mode
+    if metadata.k_anonymity_threshold > 0 && metadata.flags & FLAG_PRIVACY_MODE == 0 {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    // Rebuilt shares are checked against the reveal key commitment
+    if metadata.trustee_threshold > 0 && metadata.reveal_key_hash == [0u8; BLAKE2B_HASH_SIZE] {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    // Cohort thresholds need ballots that say which cohort they belong to
+    if metadata.cohort_k_threshold > 0
+        && (metadata.flags & FLAG_COHORT_ID == 0 || metadata.flags & FLAG_PRIVACY_MODE == 0)
+    {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    Ok(metadata)
 }
 
-/// Count existing ballots for a voter
-fn count_voter_ballots(event_id: &[u8], voter_hash: &[u8]) -> Result<u32, i8> {
-    let mut count = 0u32;
-    let mut buf = [0u8; 512];
+/// Parse the optional extension records at the end of a metadata cell
+///
+/// Each tag may appear once; a repeated tag is `ContractError::InvalidMetadata`.
+fn parse_metadata_extensions(reader: &MetadataReader, metadata: &mut ParsedMetadata) -> Result<(), ContractError> {
+    // Offsets recorded in metadata are relative to the start of the cell
+    let buf = reader.bytes();
+    let len = buf.len();
+    let mut offset = reader.extensions_offset();
+    let mut seen_tags = [false; 256];
     
-    for i in 0..1000 {
-        if load_input_by_field(&mut buf, i, CELL_FIELD_DATA).is_err() {
-            break;
+    while offset < len {
+        if offset + 2 > len {
+            return Err(ContractError::Encoding);
         }
         
+        let tag = buf[offset];
+        let value_len = buf[offset + 1] as usize;
+        let value_start = offset + 2;
+        
+        if value_start + value_len > len {
+            return Err(ContractError::Encoding);
+        }
+        
+        // A repeated record would let a later value quietly override the
+        // one an auditor read first
+        if seen_tags[tag as usize] {
+            return Err(ContractError::InvalidMetadata);
+        }
+        seen_tags[tag as usize] = true;
+        
+        let value = &buf[value_start..value_start + value_len];
+        
+        match tag {
+            METADATA_EXT_FLAGS => {
+                if value_len != 4 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.flags = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+                metadata.flags_offset = value_start;
+            }
+            METADATA_EXT_ELIGIBLE_VOTERS => {
+                if value_len != 4 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.eligible_voter_count = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            }
+            METADATA_EXT_INVITE_KEY => {
+                if value_len != PUBKEY_HASH_SIZE {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.invite_key_hash.copy_from_slice(value);
+            }
+            METADATA_EXT_QUORUM => {
+                if value_len != 4 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.quorum = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            }
+            METADATA_EXT_BREAKGLASS_KEY => {
+                if value_len != PUBKEY_HASH_SIZE {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.breakglass_hash.copy_from_slice(value);
+            }
+            METADATA_EXT_TOKEN_TYPE_HASH => {
+                if value_len != 32 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.token_type_hash.copy_from_slice(value);
+            }
+            METADATA_EXT_PAUSED => {
+                if value_len != 1 || value[0] > 1 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.paused = value[0];
+                metadata.paused_offset = value_start;
+            }
+            METADATA_EXT_REVEAL_KEY_HASH => {
+                if value_len != BLAKE2B_HASH_SIZE {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.reveal_key_hash.copy_from_slice(value);
+            }
+            METADATA_EXT_SUBMISSION_GRACE => {
+                if value_len != 4 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.submission_grace_seconds = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            }
+            METADATA_EXT_SUBMISSION_GRACE_EPOCHS => {
+                if value_len != 4 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.submission_grace_epochs = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            }
+            METADATA_EXT_SIGNATURE_SCHEME => {
+                if value_len != 1 || value[0] > SIGNATURE_SCHEME_SCHNORR {
+                    return Err(ContractError::Encoding);
+                }
+                // Schnorr verification is still a placeholder
+                if value[0] == SIGNATURE_SCHEME_SCHNORR && !PLACEHOLDER_VERIFIERS {
+                    return Err(ContractError::InvalidArgs);
+                }
+                metadata.signature_scheme = value[0];
+            }
+            METADATA_EXT_MIN_BALLOT_DEPOSIT => {
+                if value_len != 8 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.min_ballot_deposit = u64::from_le_bytes([
+                    value[0], value[1], value[2], value[3],
+                    value[4], value[5], value[6], value[7],
+                ]);
+            }
+            METADATA_EXT_BALLOT_ENCRYPTION => {
+                // [scheme: u8][ciphertext_len: u16]
+                if value_len != 3 || value[0] > ENCRYPTION_SCHEME_RSA {
+                    return Err(ContractError::Encoding);
+                }
+                let ciphertext_len = u16::from_le_bytes([value[1], value[2]]);
+                if (ciphertext_len as usize) < MIN_CIPHERTEXT_LEN || ciphertext_len as usize > BALLOT_PAYLOAD_SIZE {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.encryption_scheme = value[0];
+                metadata.ciphertext_len = ciphertext_len;
+            }
+            METADATA_EXT_BLS_AGGREGATE_PUBKEY => {
+                if value_len != BLS_PUBKEY_SIZE {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.bls_aggregate_pubkey.copy_from_slice(value);
+            }
+            METADATA_EXT_REVOTE_MODE => {
+                if value_len != 1 || value[0] > REVOTE_MODE_REPLACE {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.revote_mode = value[0];
+            }
+            METADATA_EXT_BALLOT_FORMAT => {
+                if value_len != 2 || value[0] > BALLOT_FORMAT_RANKED {
+                    return Err(ContractError::Encoding);
+                }
+                // Ranking proof verification is still a placeholder
+                if value[0] == BALLOT_FORMAT_RANKED && !PLACEHOLDER_VERIFIERS {
+                    return Err(ContractError::InvalidArgs);
+                }
+                // A ranked event must name at least one candidate
+                if value[0] == BALLOT_FORMAT_RANKED
+                    && (value[1] == 0 || value[1] as usize > MAX_VOTE_OPTIONS)
+                {
+                    return Err(ContractError::Encoding);
+                }
+                if value[1] != 0 {
+                    if metadata.candidate_count != 0 && metadata.candidate_count != value[1] {
+                        return Err(ContractError::Encoding);
+                    }
+                    metadata.candidate_count = value[1];
+                }
+                metadata.ballot_format = value[0];
+            }
+            METADATA_EXT_CANDIDATE_REGISTRY => {
+                if value_len != 2 {
+                    return Err(ContractError::Encoding);
+                }
+                // The registry must agree with any count the ballot format gave
+                let candidate_count = u16::from_le_bytes([value[0], value[1]]);
+                if candidate_count == 0
+                    || candidate_count as usize > MAX_VOTE_OPTIONS
+                    || (metadata.candidate_count != 0 && metadata.candidate_count as u16 != candidate_count)
+                {
+                    return Err(ContractError::Encoding);
+                }
+                // At most MAX_VOTE_OPTIONS, so it fits the parsed u8
+                metadata.candidate_count = candidate_count as u8;
+            }
+            METADATA_EXT_MAX_PARTICIPANTS => {
+                if value_len != 4 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.max_participants = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            }
+            METADATA_EXT_TRUSTEE_SHARES => {
+                // Share proof verification is still a placeholder
+                if !PLACEHOLDER_VERIFIERS {
+                    return Err(ContractError::InvalidArgs);
+                }
+                if value_len < 1 + SHARE_COMMITMENT_SIZE || !(value_len - 1).is_multiple_of(SHARE_COMMITMENT_SIZE) {
+                    return Err(ContractError::Encoding);
+                }
+                let trustee_count = (value_len - 1) / SHARE_COMMITMENT_SIZE;
+                if trustee_count > MAX_TRUSTEES || value[0] == 0 || value[0] as usize > trustee_count {
+                    return Err(ContractError::Encoding);
+                }
+                
+                metadata.trustee_threshold = value[0];
+                metadata.trustee_count = trustee_count;
+                for (commitment, chunk) in metadata.trustee_commitments.iter_mut()
+                    .zip(value[1..].chunks_exact(SHARE_COMMITMENT_SIZE))
+                {
+                    commitment.copy_from_slice(chunk);
+                }
+            }
+            METADATA_EXT_COHORT_K_THRESHOLD => {
+                if value_len != 2 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.cohort_k_threshold = u16::from_le_bytes([value[0], value[1]]);
+            }
+            METADATA_EXT_REGISTRATION_WINDOW => {
+                if value_len != 16 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.registration_start = u64::from_le_bytes([
+                    value[0], value[1], value[2], value[3],
+                    value[4], value[5], value[6], value[7],
+                ]);
+                metadata.registration_end = u64::from_le_bytes([
+                    value[8], value[9], value[10], value[11],
+                    value[12], value[13], value[14], value[15],
+                ]);
+            }
+            METADATA_EXT_RECOVERY => {
+                // [recovery_signer_hash: 20][recovery_unlock_time: u64]
+                if value_len != PUBKEY_HASH_SIZE + 8 {
+                    return Err(ContractError::Encoding);
+                }
+                let time = &value[PUBKEY_HASH_SIZE..];
+                metadata.recovery_signer_hash.copy_from_slice(&value[..PUBKEY_HASH_SIZE]);
+                metadata.recovery_unlock_time = u64::from_le_bytes([
+                    time[0], time[1], time[2], time[3],
+                    time[4], time[5], time[6], time[7],
+                ]);
+            }
+            METADATA_EXT_ORGANIZER_LOCK => {
+                // [organizer_lock_script_hash: 32]
+                if value_len != BLAKE2B_HASH_SIZE || value.iter().all(|&b| b == 0) {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.organizer_lock_script_hash.copy_from_slice(value);
+            }
+            METADATA_EXT_BALLOT_FEE => {
+                if value_len != 8 {
+                    return Err(ContractError::Encoding);
+                }
+                metadata.ballot_fee = u64::from_le_bytes([
+                    value[0], value[1], value[2], value[3],
+                    value[4], value[5], value[6], value[7],
+                ]);
+            }
+            _ => {
+                // Unknown configuration must not be silently ignored
+                return Err(ContractError::Encoding);
+            }
+        }
+        
+        offset = value_start + value_len;
+    }
+    
+    Ok(())
+}
+
+/// Validate the event schedule configured in metadata
+///
+/// Timestamps must be strictly increasing: voting_start < voting_end <
+/// audit_end_time. A registration window must close by voting_start,
+/// though registration_end may equal it, and fund recovery must unlock
+/// strictly after audit_end_time. Small events with the instant finalize
+/// flag may skip the audit window entirely (audit_end_time == voting_end);
+/// `parse_metadata` holds them to a curated list. A submission grace
+/// period must close before the audit period ends. Times past the sane
+/// range for the schedule basis are an encoding error.
+fn validate_schedule(metadata: &ParsedMetadata) -> Result<(), ContractError> {
+    let max_time = if metadata.flags & FLAG_EPOCH_SCHEDULE != 0 {
+        MAX_SCHEDULE_EPOCH
+    } else {
+        MAX_SCHEDULE_TIMESTAMP
+    };
+    if metadata.voting_start > max_time
+        || metadata.voting_end > max_time
+        || metadata.audit_end_time > max_time
+    {
+        return Err(ContractError::Encoding);
+    }
+    
+    if metadata.voting_start >= metadata.voting_end {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    if metadata.registration_end != 0
+        && (metadata.registration_start >= metadata.registration_end
+            || metadata.registration_end > metadata.voting_start)
+    {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    if metadata.recovery_signer_hash != [0u8; PUBKEY_HASH_SIZE] {
+        if metadata.recovery_unlock_time > max_time {
+            return Err(ContractError::Encoding);
+        }
+        if metadata.recovery_unlock_time <= metadata.audit_end_time {
+            return Err(ContractError::InvalidTiming);
+        }
+    }
+    
+    // Each grace is only meaningful on its own kind of schedule
+    let epoch_schedule = metadata.flags & FLAG_EPOCH_SCHEDULE != 0;
+    if (epoch_schedule && metadata.submission_grace_seconds > 0)
+        || (!epoch_schedule && metadata.submission_grace_epochs > 0)
+    {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    if metadata.submission_grace_seconds > 0 || metadata.submission_grace_epochs > 0 {
+        return if submission_close(metadata)? < metadata.audit_end_time {
+            Ok(())
+        } else {
+            Err(ContractError::InvalidTiming)
+        };
+    }
+    
+    if metadata.audit_end_time > metadata.voting_end {
+        return Ok(());
+    }
+    
+    if metadata.audit_end_time < metadata.voting_end {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    // audit_end_time == voting_end: only allowed for instant finalization
+    if metadata.flags & FLAG_INSTANT_FINALIZE == 0 {
+        return Err(ContractError::InvalidTiming);
+    }
+    if metadata.eligible_voter_count == 0
+        || metadata.eligible_voter_count > INSTANT_FINALIZE_MAX_VOTERS
+    {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    Ok(())
+}
+
+/// Load the current time in the units of the event's schedule
+///
+/// Header timestamps can be skewed by miners within a margin, so organizers
+/// may opt into epoch numbers instead. With FLAG_EPOCH_SCHEDULE set,
+/// voting_start, voting_end and audit_end_time are counted in epochs, and
+/// the submission grace comes from METADATA_EXT_SUBMISSION_GRACE_EPOCHS.
+fn load_schedule_time(metadata: &ParsedMetadata) -> Result<u64, ContractError> {
+    if metadata.flags & FLAG_EPOCH_SCHEDULE != 0 {
+        load_current_epoch()
+    } else {
+        load_current_timestamp()
+    }
+}
+
+/// Last schedule time at which ballots are accepted (voting_end plus grace)
+///
+/// Timestamp schedules count in milliseconds, so the grace in seconds is
+/// scaled; epoch schedules add the grace in epochs as is.
+fn submission_close(metadata: &ParsedMetadata) -> Result<u64, ContractError> {
+    let grace = if metadata.flags & FLAG_EPOCH_SCHEDULE != 0 {
+        Some(metadata.submission_grace_epochs as u64)
+    } else {
+        (metadata.submission_grace_seconds as u64).checked_mul(1000)
+    };
+    
+    grace
+        .and_then(|grace| metadata.voting_end.checked_add(grace))
+        .ok_or(ContractError::InvalidTiming)
+}
+
+/// Compute the layout of the variable-length metadata sections
+///
+/// The eligibility data block is only present in curated list mode, so the
+/// signer list (and everything after it) moves depending on the mode.
+fn parse_metadata_layout(buf: &[u8], len: usize) -> Result<MetadataLayout, ContractError> {
+    if len < METADATA_FIXED_SIZE || len > buf.len() {
+        return Err(ContractError::Encoding);
+    }
+    
+    let mut offset = METADATA_FIXED_SIZE;
+    let mut eligibility_offset = offset;
+    let mut eligibility_len = 0;
+    
+    if buf[METADATA_ELIGIBILITY_MODE_OFFSET] == ELIGIBILITY_CURATED_LIST {
+        if offset + 2 > len {
+            return Err(ContractError::Encoding);
+        }
+        eligibility_len = u16::from_le_bytes([buf[offset], buf[offset + 1]]) as usize;
+        eligibility_offset = offset + 2;
+        offset = eligibility_offset + eligibility_len;
+    }
+    
+    if offset + 1 > len {
+        return Err(ContractError::Encoding);
+    }
+    let signer_count = buf[offset] as usize;
+    let signers_offset = offset + 1;
+    let option_caps_offset = signers_offset + signer_count * PUBKEY_HASH_SIZE;
+    
+    if option_caps_offset > len {
+        return Err(ContractError::Encoding);
+    }
+    
+    // Option caps and extensions are optional trailing sections
+    let extensions_offset = if option_caps_offset < len {
+        option_caps_offset + 1 + buf[option_caps_offset] as usize * OPTION_CAP_SIZE
+    } else {
+        len
+    };
+    
+    if extensions_offset > len {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(MetadataLayout {
+        eligibility_offset,
+        eligibility_len,
+        signer_count,
+        signers_offset,
+        option_caps_offset,
+        extensions_offset,
+    })
+}
+
+impl<'a> MetadataReader<'a> {
+    /// Wrap the first `len` bytes of `buf` as a metadata cell
+    fn new(buf: &'a [u8], len: usize) -> Result<Self, ContractError> {
+        if len < METADATA_MIN_SIZE || len > buf.len() {
+            return Err(ContractError::Encoding);
+        }
+        
+        let data = &buf[..len];
+        let layout = if len > METADATA_FIXED_SIZE {
+            Some(parse_metadata_layout(data, len)?)
+        } else {
+            None
+        };
+        
+        Ok(MetadataReader { data, layout })
+    }
+    
+    /// The whole cell data
+    fn bytes(&self) -> &'a [u8] {
+        self.data
+    }
+    
+    fn event_id(&self) -> &'a [u8] {
+        &self.data[1..33]
+    }
+    
+    fn organizer_lock_hash(&self) -> &'a [u8] {
+        &self.data[33..53]
+    }
+    
+    fn voting_start(&self) -> u64 {
+        self.u64_at(53)
+    }
+    
+    fn voting_end(&self) -> u64 {
+        self.u64_at(61)
+    }
+    
+    fn audit_end_time(&self) -> u64 {
+        self.u64_at(METADATA_AUDIT_END_OFFSET)
+    }
+    
+    fn eligibility_mode(&self) -> u8 {
+        self.data[METADATA_ELIGIBILITY_MODE_OFFSET]
+    }
+    
+    fn max_revotes(&self) -> u8 {
+        self.data[78]
+    }
+    
+    fn required_signatures(&self) -> u8 {
+        self.data[79]
+    }
+    
+    fn k_anonymity_threshold(&self) -> u16 {
+        u16::from_le_bytes([self.data[80], self.data[81]])
+    }
+    
+    /// Frontend code hash, absent on cells that stop at the minimal core
+    fn frontend_code_hash(&self) -> Option<&'a [u8]> {
+        self.data.get(82..METADATA_FIXED_SIZE)
+    }
+    
+    /// Number of authorized signers (0 when the cell has no signer list)
+    fn signer_count(&self) -> usize {
+        match &self.layout {
+            Some(layout) => layout.signer_count,
+            None => 0,
+        }
+    }
+    
+    /// Lock hash of the i-th authorized signer
+    fn signer(&self, i: usize) -> Result<&'a [u8], ContractError> {
+        let layout = match &self.layout {
+            Some(layout) if i < layout.signer_count => layout,
+            _ => return Err(ContractError::Encoding),
+        };
+        
+        let offset = layout.signers_offset + i * PUBKEY_HASH_SIZE;
+        self.data
+            .get(offset..offset + PUBKEY_HASH_SIZE)
+            .ok_or(ContractError::Encoding)
+    }
+    
+    /// Curated eligibility block (empty outside curated list mode)
+    fn eligibility_data(&self) -> &'a [u8] {
+        match &self.layout {
+            Some(layout) => &self.data[layout.eligibility_offset..layout.eligibility_offset + layout.eligibility_len],
+            None => &[],
+        }
+    }
+    
+    /// Number of per-option caps declared (0 when the section is absent)
+    fn option_cap_count(&self) -> usize {
+        match &self.layout {
+            Some(layout) if layout.option_caps_offset < self.data.len() => {
+                self.data[layout.option_caps_offset] as usize
+            }
+            _ => 0,
+        }
+    }
+    
+    /// Vote cap of the i-th option
+    fn option_cap(&self, i: usize) -> Result<u32, ContractError> {
+        let layout = match &self.layout {
+            Some(layout) if i < self.option_cap_count() => layout,
+            _ => return Err(ContractError::Encoding),
+        };
+        
+        let offset = layout.option_caps_offset + 1 + i * OPTION_CAP_SIZE;
+        match self.data.get(offset..offset + 4) {
+            Some(cap) => Ok(u32::from_le_bytes([cap[0], cap[1], cap[2], cap[3]])),
+            None => Err(ContractError::Encoding),
+        }
+    }
+    
+    /// Offset of the first extension record (the cell length if none)
+    fn extensions_offset(&self) -> usize {
+        match &self.layout {
+            Some(layout) => layout.extensions_offset,
+            None => self.data.len(),
+        }
+    }
+    
+    fn u64_at(&self, offset: usize) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.data[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// Load the authorized result-release signers for an event
+/// Returns the number of signers written into `signers`
+fn load_authorized_signers(
+    event_id: &[u8],
+    signers: &mut [[u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS],
+) -> Result<usize, ContractError> {
+    let mut buf = [0u8; MAX_METADATA_SIZE];
+    let len = load_metadata_bytes(event_id, &mut buf)?;
+    let reader = MetadataReader::new(&buf, len)?;
+    
+    let signer_count = reader.signer_count();
+    if signer_count > MAX_AUTHORIZED_SIGNERS {
+        return Err(ContractError::Encoding);
+    }
+    
+    for (i, signer) in signers.iter_mut().enumerate().take(signer_count) {
+        signer.copy_from_slice(reader.signer(i)?);
+    }
+    
+    Ok(signer_count)
+}
+
+/// Find the index of the first output cell of a given type for an event
+fn find_output_index(cell_type: u8, event_id: &[u8]) -> Result<Option<usize>, ContractError> {
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if prefix_matches(&buf, len, cell_type, event_id, None) {
+            return Ok(Some(i));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Look up a voter's leaf in the curated eligibility list
+/// Returns the voter's maximum ballot weight, or None if they aren't listed
+fn find_curated_weight_cap(event_id: &[u8], voter_hash: &[u8]) -> Result<Option<u32>, ContractError> {
+    let mut buf = [0u8; MAX_METADATA_SIZE];
+    let len = load_metadata_bytes(event_id, &mut buf)?;
+    let eligibility_data = MetadataReader::new(&buf, len)?.eligibility_data();
+    
+    if eligibility_data.len() % CURATED_LEAF_SIZE != 0 {
+        return Err(ContractError::Encoding);
+    }
+    
+    for leaf in eligibility_data.chunks_exact(CURATED_LEAF_SIZE) {
+        if bytes_equal(&leaf[..PUBKEY_HASH_SIZE], voter_hash) {
+            let cap = &leaf[PUBKEY_HASH_SIZE..PUBKEY_HASH_SIZE + 4];
+            return Ok(Some(u32::from_le_bytes([cap[0], cap[1], cap[2], cap[3]])));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Ballot payload length for an event (the declared ciphertext length)
+fn ballot_payload_size(metadata: &ParsedMetadata) -> usize {
+    if metadata.ciphertext_len == 0 {
+        BALLOT_PAYLOAD_SIZE
+    } else {
+        metadata.ciphertext_len as usize
+    }
+}
+
+/// Exact voter cell length for an event with a declared ciphertext length
+fn expected_ballot_len(metadata: &ParsedMetadata) -> usize {
+    let mut len = BALLOT_PAYLOAD_OFFSET + ballot_payload_size(metadata);
+    
+    if metadata.flags & FLAG_WEIGHTED_VOTING != 0 {
+        len += 4;
+    }
+    if metadata.flags & FLAG_FUND_BINDING != 0 {
+        len += FUND_REF_SIZE;
+    }
+    if metadata.flags & FLAG_COHORT_ID != 0 {
+        len += COHORT_ID_SIZE;
+    }
+    if metadata.flags & FLAG_AUDIT_CHAIN != 0 {
+        len += BLAKE2B_HASH_SIZE;
+    }
+    
+    len
+}
+
+/// Offset of the fund reference in a voter cell (FLAG_FUND_BINDING)
+fn ballot_fund_ref_offset(metadata: &ParsedMetadata) -> usize {
+    let mut offset = BALLOT_PAYLOAD_OFFSET + ballot_payload_size(metadata);
+    
+    if metadata.flags & FLAG_WEIGHTED_VOTING != 0 {
+        offset += 4;
+    }
+    
+    offset
+}
+
+/// Offset of the cohort id in a voter cell (FLAG_COHORT_ID)
+fn ballot_cohort_offset(metadata: &ParsedMetadata) -> usize {
+    let mut offset = ballot_fund_ref_offset(metadata);
+    
+    if metadata.flags & FLAG_FUND_BINDING != 0 {
+        offset += FUND_REF_SIZE;
+    }
+    
+    offset
+}
+
+/// Read the EventFund input index a voter cell claims it was paid from
+fn ballot_fund_ref(ballot_data: &[u8], len: usize, offset: usize) -> Result<usize, ContractError> {
+    if len < offset + FUND_REF_SIZE || ballot_data.len() < offset + FUND_REF_SIZE {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(u32::from_le_bytes([
+        ballot_data[offset],
+        ballot_data[offset + 1],
+        ballot_data[offset + 2],
+        ballot_data[offset + 3],
+    ]) as usize)
+}
+
+/// Read the weight of a loaded voter cell
+/// Unweighted events count every ballot as 1
+fn ballot_weight(ballot_data: &[u8], len: usize, weighted: bool, payload_size: usize) -> Result<u32, ContractError> {
+    if !weighted {
+        return Ok(1);
+    }
+    
+    let weight_offset = BALLOT_PAYLOAD_OFFSET + payload_size;
+    if len < weight_offset + 4 || ballot_data.len() < weight_offset + 4 {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(u32::from_le_bytes([
+        ballot_data[weight_offset],
+        ballot_data[weight_offset + 1],
+        ballot_data[weight_offset + 2],
+        ballot_data[weight_offset + 3],
+    ]))
+}
+
+/// Sum the weights of the latest input ballot of each voter for an event
+fn sum_voter_weights(event_id: &[u8], payload_size: usize) -> Result<u64, ContractError> {
+    let mut total = 0u64;
+    let mut runs = VoterRuns::new(event_id);
+    let mut buf = [0u8; BALLOT_WEIGHT_OFFSET + 4];
+    
+    for i in 0.. {
+        let len = match scan_cell_data(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if !is_event_voter_cell(&buf, len, event_id)? || !runs.is_latest(i)? {
+            continue;
+        }
+        
+        total += ballot_weight(&buf, len, true, payload_size)? as u64;
+    }
+    
+    Ok(total)
+}
+
+/// One voter's input cells: the first and the latest ballot
+#[derive(Clone, Copy)]
+struct VoterRun {
+    first: usize,
+    latest: usize,
+}
+
+/// Walks an event's input voter cells one voter at a time
+///
+/// Input ballots must be sorted by voter hash and then strictly by sequence
+/// number, so each voter's cells form a single run ending in their latest
+/// ballot and one pass over the inputs finds it without remembering every
+/// voter seen so far. Cells out of that order, or two with the same voter
+/// and sequence number, fail with `ContractError::InvalidSequence`.
+struct VoterRuns<'a> {
+    event_id: &'a [u8],
+    next_index: usize,
+    // The first cell of the next run, read while finding the current run's end
+    pending: Option<(usize, [u8; PUBKEY_HASH_SIZE], u32)>,
+    current: Option<VoterRun>,
+}
+
+impl<'a> VoterRuns<'a> {
+    fn new(event_id: &'a [u8]) -> Self {
+        VoterRuns {
+            event_id,
+            next_index: 0,
+            pending: None,
+            current: None,
+        }
+    }
+    
+    /// The next voter's run of input cells, or None past the last voter
+    fn next_run(&mut self) -> Result<Option<VoterRun>, ContractError> {
+        let (first, voter_hash, mut sequence) = match self.pending.take() {
+            Some(cell) => cell,
+            None => match self.next_voter_cell()? {
+                Some(cell) => cell,
+                None => return Ok(None),
+            },
+        };
+        let mut latest = first;
+        
+        while let Some((index, other_hash, other_sequence)) = self.next_voter_cell()? {
+            if other_hash != voter_hash {
+                if other_hash < voter_hash {
+                    return Err(ContractError::InvalidSequence);
+                }
+                self.pending = Some((index, other_hash, other_sequence));
+                break;
+            }
+            if other_sequence <= sequence {
+                return Err(ContractError::InvalidSequence);
+            }
+            latest = index;
+            sequence = other_sequence;
+        }
+        
+        Ok(Some(VoterRun { first, latest }))
+    }
+    
+    /// The run holding the input voter cell at `index`
+    ///
+    /// Callers pass voter cell indexes in increasing order.
+    fn run_of(&mut self, index: usize) -> Result<VoterRun, ContractError> {
+        loop {
+            if let Some(run) = self.current {
+                if run.latest >= index {
+                    return Ok(run);
+                }
+            }
+            self.current = match self.next_run()? {
+                Some(run) => Some(run),
+                None => return Err(ContractError::Encoding),
+            };
+        }
+    }
+    
+    /// Whether the input voter cell at `index` is its voter's latest ballot
+    ///
+    /// Only a voter's latest ballot counts; earlier ones are superseded.
+    fn is_latest(&mut self, index: usize) -> Result<bool, ContractError> {
+        Ok(self.run_of(index)?.latest == index)
+    }
+    
+    fn next_voter_cell(&mut self) -> Result<Option<(usize, [u8; PUBKEY_HASH_SIZE], u32)>, ContractError> {
+        let mut buf = [0u8; BALLOT_SEQUENCE_OFFSET + 4];
+        
+        loop {
+            let index = self.next_index;
+            let len = match scan_cell_data(&mut buf, index, SOURCE_INPUT)? {
+                Some(len) => len,
+                None => return Ok(None),
+            };
+            self.next_index += 1;
+            
+            if !is_event_voter_cell(&buf, len, self.event_id)? {
+                continue;
+            }
+            if len < buf.len() {
+                return Err(ContractError::Encoding);
+            }
+            
+            let mut voter_hash = [0u8; PUBKEY_HASH_SIZE];
+            voter_hash.copy_from_slice(&buf[33..53]);
+            return Ok(Some((index, voter_hash, ballot_sequence_number(&buf))));
+        }
+    }
+}
+
+/// Check whether any input cell carries a type script with the given hash
+fn has_input_with_type_hash(type_hash: &[u8; 32]) -> Result<bool, ContractError> {
+    let mut hash = [0u8; 32];
+    
+    for i in 0.. {
+        // Inputs without a type script have no type hash
+        match try_load_cell_by_field_at(&mut hash, 0, i, SOURCE_INPUT, CELL_FIELD_TYPE_HASH) {
+            LoadResult::Ok(32) => {}
+            LoadResult::ItemMissing => continue,
+            LoadResult::OutOfBound => break,
+            _ => return Err(ContractError::Syscall),
+        }
+        
+        if bytes_equal(&hash, type_hash) {
+            return Ok(true);
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Count a voter's ballot cells in the given source
+///
+/// Scans the whole source rather than the script group so inputs and
+/// outputs, which a lock script has no group for, are counted alike.
+fn count_voter_ballots(event_id: &[u8], voter_hash: &[u8], source: u64) -> Result<u32, ContractError> {
+    let mut count = 0u32;
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, source)? {
+            Some(len) => len,
+            None => break,
+        };
+        
         // Check if this is a voter cell for our event and voter
-        if buf[0] == VOTER_TYPE 
-            && bytes_equal(&buf[1..33], event_id)
+        if is_event_voter_cell(&buf, len, event_id)?
             && bytes_equal(&buf[33..53], voter_hash)
         {
             count += 1;
         }
     }
     
-    Ok(count)
+    Ok(count)
+}
+
+/// Find the highest sequence number among a voter's input ballots
+/// Returns None when the voter has no prior ballot in the inputs
+fn max_prior_sequence(event_id: &[u8], voter_hash: &[u8]) -> Result<Option<u32>, ContractError> {
+    let mut max_sequence: Option<u32> = None;
+    let mut buf = [0u8; BALLOT_SEQUENCE_OFFSET + 4];
+    
+    for i in 0.. {
+        let len = match scan_cell_data(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len < buf.len()
+            || !is_event_voter_cell(&buf, len, event_id)?
+            || !bytes_equal(&buf[33..53], voter_hash)
+        {
+            continue;
+        }
+        
+        let sequence = ballot_sequence_number(&buf);
+        max_sequence = match max_sequence {
+            Some(max) if max >= sequence => Some(max),
+            _ => Some(sequence),
+        };
+    }
+    
+    Ok(max_sequence)
+}
+
+/// Find the latest timestamp among a voter's input ballots
+/// Returns None when the voter has no prior ballot in the inputs
+fn max_prior_timestamp(event_id: &[u8], voter_hash: &[u8]) -> Result<Option<u64>, ContractError> {
+    let mut max_timestamp: Option<u64> = None;
+    let mut buf = [0u8; BALLOT_TIMESTAMP_OFFSET + 8];
+    
+    for i in 0.. {
+        let len = match scan_cell_data(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len < buf.len()
+            || !is_event_voter_cell(&buf, len, event_id)?
+            || !bytes_equal(&buf[33..53], voter_hash)
+        {
+            continue;
+        }
+        
+        let timestamp = ballot_timestamp(&buf);
+        max_timestamp = match max_timestamp {
+            Some(max) if max >= timestamp => Some(max),
+            _ => Some(timestamp),
+        };
+    }
+    
+    Ok(max_timestamp)
+}
+
+/// Load per-option vote caps for an event
+/// Returns the number of options declared (0 = no caps configured)
+fn load_option_caps(event_id: &[u8], caps: &mut [u32; MAX_VOTE_OPTIONS]) -> Result<usize, ContractError> {
+    let mut buf = [0u8; MAX_METADATA_SIZE];
+    let len = load_metadata_bytes(event_id, &mut buf)?;
+    let reader = MetadataReader::new(&buf, len)?;
+    
+    // Caps section is optional: metadata may end after the signer list
+    let option_count = reader.option_cap_count();
+    if option_count > MAX_VOTE_OPTIONS {
+        return Err(ContractError::Encoding);
+    }
+    
+    for (i, cap) in caps.iter_mut().enumerate().take(option_count) {
+        *cap = reader.option_cap(i)?;
+    }
+    
+    Ok(option_count)
+}
+
+/// Count the output cells of a given type for an event
+fn count_output_cells(cell_type: u8, event_id: &[u8]) -> Result<u32, ContractError> {
+    let mut count = 0u32;
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if prefix_matches(&buf, len, cell_type, event_id, None) {
+            count += 1;
+        }
+    }
+    
+    Ok(count)
+}
+
+/// Find the output result cell for an event, loading its data into buf
+fn find_result_output(event_id: &[u8], buf: &mut [u8]) -> Result<usize, ContractError> {
+    for i in 0.. {
+        let len = match scan_cell_data(buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len >= 33 && cell_type_of(buf[0]) == RESULT_TYPE && bytes_equal(&buf[1..33], event_id) {
+            return Ok(len);
+        }
+    }
+    
+    Err(ContractError::InvalidTally)
+}
+
+/// Parse the vote tallies out of result cell data
+/// Returns the number of options in the tally
+fn parse_result_tallies(result_data: &[u8], tallies: &mut [u32; MAX_VOTE_OPTIONS]) -> Result<usize, ContractError> {
+    if result_data.len() <= RESULT_TALLY_COUNT_OFFSET {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    let tally_count = result_data[RESULT_TALLY_COUNT_OFFSET] as usize;
+    if tally_count > MAX_VOTE_OPTIONS {
+        return Err(ContractError::InvalidTally);
+    }
+    if RESULT_TALLIES_OFFSET + tally_count * TALLY_SIZE > result_data.len() {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    for (i, tally) in tallies.iter_mut().enumerate().take(tally_count) {
+        let offset = RESULT_TALLIES_OFFSET + i * TALLY_SIZE;
+        *tally = u32::from_le_bytes([
+            result_data[offset], result_data[offset + 1],
+            result_data[offset + 2], result_data[offset + 3],
+        ]);
+    }
+    
+    Ok(tally_count)
+}
+
+/// Parse the tally count and Merkle root of a committed-tally result cell
+fn parse_result_tally_root(result_data: &[u8]) -> Result<(usize, [u8; BLAKE2B_HASH_SIZE]), ContractError> {
+    if RESULT_TALLY_ROOT_OFFSET + BLAKE2B_HASH_SIZE > result_data.len() {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    let tally_count = result_data[RESULT_TALLY_COUNT_OFFSET] as usize;
+    if tally_count == 0 || tally_count > MAX_VOTE_OPTIONS {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    let mut root = [0u8; BLAKE2B_HASH_SIZE];
+    root.copy_from_slice(&result_data[RESULT_TALLY_ROOT_OFFSET..RESULT_TALLY_ROOT_OFFSET + BLAKE2B_HASH_SIZE]);
+    
+    Ok((tally_count, root))
+}
+
+/// Merkle leaf committing to one option's tally: blake2b(option || tally)
+fn tally_leaf(option: usize, tally: u32) -> [u8; BLAKE2B_HASH_SIZE] {
+    let mut preimage = [0u8; 1 + TALLY_SIZE];
+    preimage[0] = option as u8;
+    preimage[1..].copy_from_slice(&tally.to_le_bytes());
+    
+    let mut leaf = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage, &mut leaf);
+    leaf
+}
+
+/// Depth of the tally tree: the smallest power of two covering every option
+fn tally_tree_depth(tally_count: usize) -> usize {
+    let mut depth = 0;
+    while (1usize << depth) < tally_count {
+        depth += 1;
+    }
+    depth
+}
+
+/// Verify a Merkle proof that a tally leaf is in the committed tree
+///
+/// The proof is the sibling hashes from the leaf up to the root. Bit k of
+/// `index` (the option) says whether the node at level k is a right child,
+/// so every option has exactly one position in the tree.
+fn verify_tally_commitment(
+    root: &[u8; BLAKE2B_HASH_SIZE],
+    tally_leaf: &[u8; BLAKE2B_HASH_SIZE],
+    index: usize,
+    proof: &[u8],
+) -> bool {
+    if !proof.len().is_multiple_of(BLAKE2B_HASH_SIZE) {
+        return false;
+    }
+    
+    let mut node = *tally_leaf;
+    let mut preimage = [0u8; 2 * BLAKE2B_HASH_SIZE];
+    
+    for (level, sibling) in proof.chunks_exact(BLAKE2B_HASH_SIZE).enumerate() {
+        if (index >> level) & 1 == 0 {
+            preimage[..BLAKE2B_HASH_SIZE].copy_from_slice(&node);
+            preimage[BLAKE2B_HASH_SIZE..].copy_from_slice(sibling);
+        } else {
+            preimage[..BLAKE2B_HASH_SIZE].copy_from_slice(sibling);
+            preimage[BLAKE2B_HASH_SIZE..].copy_from_slice(&node);
+        }
+        blake2b_hash(&preimage, &mut node);
+    }
+    
+    bytes_equal_ct(&node, root)
+}
+
+/// Verify that no option in the released tally exceeds its configured cap
+fn verify_option_caps(event_id: &[u8], tally_counts: &TallyCounts) -> Result<(), ContractError> {
+    let mut caps = [0u32; MAX_VOTE_OPTIONS];
+    let option_count = load_option_caps(event_id, &mut caps)?;
+    
+    if option_count == 0 {
+        return Ok(());
+    }
+    
+    let tally_count = tally_counts.tally_count;
+    if tally_count != option_count {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    for (&cap, &tally) in caps.iter().zip(tally_counts.tallies.iter()).take(tally_count) {
+        if cap != 0 && tally > cap {
+            return Err(ContractError::InvalidTally);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Verify the released tally against the input voter cells
+///
+/// Ballots are encrypted, so the release witness reveals each ballot's
+/// decrypted option together with the salt it was committed with. Reveals
+/// are matched to voter cells in input order; each must open the
+/// commitment at the start of the ballot payload, and the accumulated
+/// counts must equal the tallies in the result cell. Only each voter's
+/// latest ballot is counted and revealed; in append mode the ballots it
+/// superseded are still inputs but are skipped. Ballots revealing
+/// `ABSTAIN_OPTION` are counted as abstentions rather than in any tally.
+/// For weighted events each ballot adds its weight instead of 1, to the
+/// abstentions as much as to the tallies.
+///
+/// Each ballot's creation block must be attached as a header dep: it is
+/// the one time for a ballot no transaction builder can pick, so ballots
+/// created after submissions closed are rejected here whatever time the
+/// transaction that created them claimed.
+///
+/// With FLAG_TALLY_COMMITMENT the result holds only a Merkle root; the
+/// reveal is then followed by one proof per option, in option order, each
+/// `tally_tree_depth` sibling hashes long, and every counted tally must be
+/// proven against the root.
+fn verify_tally(
+    event_id: &[u8],
+    metadata: &ParsedMetadata,
+    result_data: &[u8],
+    reveal_data: &[u8],
+) -> Result<TallyCounts, ContractError> {
+    let weighted = metadata.flags & FLAG_WEIGHTED_VOTING != 0;
+    let committed = metadata.flags & FLAG_TALLY_COMMITMENT != 0;
+    let payload_size = ballot_payload_size(metadata);
+    let voting_close = submission_close(metadata)?;
+    
+    let mut claimed = [0u32; MAX_VOTE_OPTIONS];
+    let mut root = [0u8; BLAKE2B_HASH_SIZE];
+    let tally_count = if committed {
+        let (count, committed_root) = parse_result_tally_root(result_data)?;
+        root = committed_root;
+        count
+    } else {
+        parse_result_tallies(result_data, &mut claimed)?
+    };
+    
+    // Reveal format: [reveal_count: u16][[option: u8][salt: 32]]...
+    if reveal_data.len() < 2 {
+        return Err(ContractError::InvalidTally);
+    }
+    let reveal_count = u16::from_le_bytes([reveal_data[0], reveal_data[1]]) as usize;
+    let proofs_offset = 2 + reveal_count * TALLY_REVEAL_ENTRY_SIZE;
+    if proofs_offset > reveal_data.len() {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    let mut counted = [0u32; MAX_VOTE_OPTIONS];
+    let mut abstentions = 0u64;
+    let mut counted_weight = 0u64;
+    let mut revealed = 0usize;
+    let mut runs = VoterRuns::new(event_id);
+    let mut buf = [0u8; 512];
+    
+    for i in 0.. {
+        let len = match scan_cell_data(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        // Short cells leave stale bytes from earlier loads in the buffer,
+        // so check the length before reading the prefix
+        if !is_event_voter_cell(&buf, len, event_id)? {
+            continue;
+        }
+        
+        if load_cell_schedule_time(metadata, i, SOURCE_INPUT)? > voting_close {
+            return Err(ContractError::InvalidTiming);
+        }
+        
+        // Only a voter's latest ballot is tallied; superseded ones have
+        // no reveal
+        if !runs.is_latest(i)? {
+            continue;
+        }
+        
+        if revealed >= reveal_count {
+            return Err(ContractError::InvalidTally);
+        }
+        if len < BALLOT_PAYLOAD_OFFSET + BALLOT_COMMITMENT_SIZE {
+            return Err(ContractError::Encoding);
+        }
+        
+        let entry_offset = 2 + revealed * TALLY_REVEAL_ENTRY_SIZE;
+        let entry = &reveal_data[entry_offset..entry_offset + TALLY_REVEAL_ENTRY_SIZE];
+        let option = entry[0];
+        
+        if option != ABSTAIN_OPTION && option as usize >= tally_count {
+            return Err(ContractError::InvalidTally);
+        }
+        
+        let mut commitment = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(entry, &mut commitment);
+        
+        let ballot_commitment = &buf[BALLOT_PAYLOAD_OFFSET..BALLOT_PAYLOAD_OFFSET + BALLOT_COMMITMENT_SIZE];
+        if !bytes_equal_ct(&commitment, ballot_commitment) {
+            return Err(ContractError::InvalidTally);
+        }
+        
+        // The opened commitment proves the voter selected this option, so
+        // one outside the candidate registry is a malformed ballot
+        if option != ABSTAIN_OPTION && metadata.candidate_count != 0 && option >= metadata.candidate_count {
+            return Err(ContractError::Encoding);
+        }
+        
+        let weight = ballot_weight(&buf, len, weighted, payload_size)?;
+        counted_weight += weight as u64;
+        
+        if option == ABSTAIN_OPTION {
+            abstentions += weight as u64;
+        } else {
+            counted[option as usize] = match counted[option as usize].checked_add(weight) {
+                Some(total) => total,
+                None => return Err(ContractError::InvalidTally),
+            };
+        }
+        revealed += 1;
+    }
+    
+    // Every reveal must correspond to a voter cell
+    if revealed != reveal_count {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    // Weighted totals must account for the full weight cast for the event
+    if weighted && counted_weight != sum_voter_weights(event_id, payload_size)? {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    if committed {
+        let proof_size = tally_tree_depth(tally_count) * BLAKE2B_HASH_SIZE;
+        if proofs_offset + tally_count * proof_size > reveal_data.len() {
+            return Err(ContractError::InvalidTally);
+        }
+        
+        for (i, &count) in counted.iter().enumerate().take(tally_count) {
+            let proof_start = proofs_offset + i * proof_size;
+            let proof = &reveal_data[proof_start..proof_start + proof_size];
+            if !verify_tally_commitment(&root, &tally_leaf(i, count), i, proof) {
+                return Err(ContractError::InvalidTally);
+            }
+        }
+    } else {
+        for i in 0..tally_count {
+            if counted[i] != claimed[i] {
+                return Err(ContractError::InvalidTally);
+            }
+        }
+    }
+    
+    Ok(TallyCounts {
+        ballots: counted_weight,
+        abstentions,
+        tally_count,
+        tallies: counted,
+    })
+}
+
+/// Check whether a pubkey hash is one of the authorized signers
+fn is_authorized_signer(
+    hash: &[u8],
+    signers: &[[u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS],
+    signer_count: usize,
+) -> bool {
+    for authorized_hash in signers.iter().take(signer_count) {
+        if bytes_equal_ct(hash, authorized_hash) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Record a signer as having signed, returning false if they already did
+///
+/// Prevents one signer from supplying several copies of their signature to
+/// reach a multisig threshold.
+fn mark_signer_seen(
+    seen: &mut [[u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS],
+    seen_count: &mut usize,
+    hash: &[u8; PUBKEY_HASH_SIZE],
+) -> bool {
+    for seen_hash in seen.iter().take(*seen_count) {
+        if bytes_equal(seen_hash, hash) {
+            return false;
+        }
+    }
+    
+    if *seen_count >= MAX_AUTHORIZED_SIGNERS {
+        return false;
+    }
+    
+    seen[*seen_count] = *hash;
+    *seen_count += 1;
+    true
+}
+
+/// Size of one multisig entry for a signature scheme
+fn signer_entry_size(scheme: u8) -> usize {
+    if scheme == SIGNATURE_SCHEME_SCHNORR {
+        XONLY_PUBKEY_SIZE + SIGNATURE_SIZE
+    } else {
+        SIGNER_ENTRY_SIZE
+    }
+}
+
+/// Compute the signer pubkey hash identifying a multisig entry
+///
+/// Signers are listed in metadata by the blake160 of their key, whether
+/// that is a compressed ECDSA key or a Schnorr x-only key.
+fn signer_entry_hash(entry: &[u8], scheme: u8) -> [u8; PUBKEY_HASH_SIZE] {
+    let key_size = signer_entry_size(scheme) - SIGNATURE_SIZE;
+    let mut hash = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&entry[..key_size], &mut hash);
+    
+    let mut result = [0u8; PUBKEY_HASH_SIZE];
+    result.copy_from_slice(&hash[..PUBKEY_HASH_SIZE]);
+    result
+}
+
+/// Verify the signature in a multisig entry under the event's scheme
+fn verify_signer_entry(entry: &[u8], scheme: u8, message_hash: &[u8; 32]) -> bool {
+    if scheme == SIGNATURE_SCHEME_SCHNORR {
+        verify_schnorr_signature(
+            &entry[..XONLY_PUBKEY_SIZE],
+            &entry[XONLY_PUBKEY_SIZE..XONLY_PUBKEY_SIZE + SIGNATURE_SIZE],
+            message_hash,
+        )
+    } else {
+        verify_secp256k1_signature(
+            &entry[..PUBKEY_SIZE],
+            &entry[PUBKEY_SIZE..PUBKEY_SIZE + SIGNATURE_SIZE],
+            message_hash,
+        )
+    }
+}
+
+/// Locate a multisig witness lock and read its signature count
+///
+/// Returns (lock_start, lock_len, sig_count) for the witness at index.
+fn load_multisig_header(index: usize) -> Result<(usize, usize, u8), ContractError> {
+    let (lock_start, lock_len) = match locate_witness_lock(index) {
+        Ok(lock) => lock,
+        // A witness that isn't well-formed is malformed, not unsigned
+        Err(ContractError::Encoding) => return Err(ContractError::Encoding),
+        Err(e) => return Err(witness_error(e, ContractError::InsufficientSignatures)),
+    };
+    if lock_len < 1 {
+        return Err(ContractError::EmptyWitness);
+    }
+    
+    let mut count_buf = [0u8; 1];
+    load_witness_range(&mut count_buf, lock_start, index)?;
+    
+    Ok((lock_start, lock_len, count_buf[0]))
+}
+
+/// Check a multisig lock of `lock_len` bytes holds `sig_count` entries
+///
+/// Pure bounds check on the signature window, done before any entry is
+/// loaded. A count the lock can't hold is malformed (`Encoding`), as
+/// opposed to too few signatures. Returns the size of one entry under
+/// `scheme`.
+fn parse_signer_window(lock_len: usize, sig_count: u8, scheme: u8) -> Result<usize, ContractError> {
+    let entry_size = signer_entry_size(scheme);
+    
+    if 1 + sig_count as usize * entry_size > lock_len {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(entry_size)
+}
+
+/// Verify the multisig lock in witness 0 against the authorized signer set
+///
+/// Format: [sig_count: u8][[pubkey][sig: 64]]... where the pubkey is 33
+/// bytes for ECDSA and 32 bytes (x-only) for Schnorr. Every entry must come
+/// from a distinct authorized signer and sign the transaction hash.
+///
+/// Entries are loaded and verified one at a time through a small buffer,
+/// so large signer sets don't need the whole lock in memory.
+fn verify_signer_signatures(
+    lock_start: usize,
+    lock_len: usize,
+    sig_count: u8,
+    scheme: u8,
+    signers: &[[u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS],
+    signer_count: usize,
+    tx_hash: &[u8; 32],
+) -> Result<(), ContractError> {
+    // Each authorized signer may only be counted once
+    let mut seen = [[0u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS];
+    let mut seen_count = 0usize;
+    let entry_size = parse_signer_window(lock_len, sig_count, scheme)?;
+    let mut entry_buf = [0u8; SIGNER_ENTRY_SIZE];
+    
+    for i in 0..sig_count {
+        let witness_offset = 1 + (i as usize * entry_size);
+        
+        load_witness_range(&mut entry_buf[..entry_size], lock_start + witness_offset, 0)?;
+        let witness_sig = &entry_buf[..entry_size];
+        
+        // Extract pubkey hash from witness signature
+        let computed_hash = signer_entry_hash(witness_sig, scheme);
+        
+        // Verify this signer is authorized
+        if !is_authorized_signer(&computed_hash, signers, signer_count) {
+            return Err(ContractError::InvalidSignature);
+        }
+        
+        // Reject a signer appearing more than once
+        if !mark_signer_seen(&mut seen, &mut seen_count, &computed_hash) {
+            return Err(ContractError::InvalidSignature);
+        }
+        
+        // Verify the signature
+        if !verify_signer_entry(witness_sig, scheme, tx_hash) {
+            return Err(ContractError::InvalidSignature);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Count voter cells for an event across all inputs
+///
+/// Stops scanning once `limit` cells are counted, for callers that only
+/// compare the count against a threshold; the count is exact below it.
+/// The released result needs an exact turnout and uses
+/// `count_unique_voters` instead.
+fn count_event_voters(event_id: &[u8], limit: u32) -> Result<u32, ContractError> {
+    let mut voter_count = 0u32;
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        if voter_count >= limit {
+            break;
+        }
+        
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        // Cells too short for a voter layout are not ballots
+        if is_event_voter_cell(&buf, len, event_id)? {
+            voter_count += 1;
+        }
+    }
+    
+    Ok(voter_count)
+}
+
+/// Check the transaction creates no voter cells once submissions closed
+///
+/// A voter cell's lock only runs when the cell is spent, so a ballot output
+/// in a transaction this script instance didn't validate as a ballot never
+/// meets the schedule check in `verify_voter_ballot`. Operations that run
+/// after voting ends scan the outputs for one instead.
+fn verify_no_late_ballots(event_id: &[u8], metadata: &ParsedMetadata, current_time: u64) -> Result<(), ContractError> {
+    if current_time <= submission_close(metadata)? {
+        return Ok(());
+    }
+    
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if is_event_voter_cell(&buf, len, event_id)? {
+            return Err(ContractError::InvalidTiming);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Count the distinct voters among an event's input voter cells
+///
+/// Several cells for the same voter (e.g. a ballot and its revotes) count as
+/// one participant. Inputs must be in `VoterRuns` order, so there is no
+/// bound on how many voters can be counted.
+fn count_unique_voters(event_id: &[u8]) -> Result<u32, ContractError> {
+    let mut runs = VoterRuns::new(event_id);
+    let mut count = 0u32;
+    while runs.next_run()?.is_some() {
+        count += 1;
+    }
+    
+    Ok(count)
+}
+
+/// Check every cohort with input ballots meets the cohort threshold
+///
+/// Voters are counted once, in the cohort of their first input cell, so a
+/// revote can't move a voter into a second cohort to pad it. Unsegmented
+/// ballots (cohort 0) are left to the global threshold.
+fn verify_cohort_k_anonymity(event_id: &[u8], metadata: &ParsedMetadata) -> Result<(), ContractError> {
+    if metadata.cohort_k_threshold == 0 {
+        return Ok(());
+    }
+    
+    let cohort_offset = ballot_cohort_offset(metadata);
+    let mut runs = VoterRuns::new(event_id);
+    let mut cohort_counts = [0u32; 256];
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    let mut cohort = [0u8; COHORT_ID_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if !is_event_voter_cell(&buf, len, event_id)? {
+            continue;
+        }
+        
+        if runs.run_of(i)?.first != i {
+            continue;
+        }
+        
+        if len < cohort_offset + COHORT_ID_SIZE {
+            return Err(ContractError::Encoding);
+        }
+        load_cell_data_at(&mut cohort, cohort_offset, i, SOURCE_INPUT)?;
+        cohort_counts[cohort[0] as usize] += 1;
+    }
+    
+    for (id, &count) in cohort_counts.iter().enumerate() {
+        if id as u8 == COHORT_UNSEGMENTED || count == 0 {
+            continue;
+        }
+        if count < metadata.cohort_k_threshold as u32 {
+            return Err(ContractError::KAnonymityViolation);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Load a signed turnout attestation for an event from cell deps
+///
+/// Returns `Ok(Some((voter_count, ballot_root)))` when an attestation is
+/// present and signed by at least `required_signatures` authorized signers,
+/// `Ok(None)` when no attestation is present or it doesn't meet the
+/// threshold (callers fall back to scanning inputs), and
+/// `ContractError::InvalidSignature` when any attached signature is
+/// unauthorized or invalid. Two attestations for the event could claim
+/// different turnouts, so attaching more than one fails with
+/// `ContractError::AmbiguousAttestation`.
+fn load_turnout_attestation(
+    event_id: &[u8],
+    metadata: &ParsedMetadata,
+    signers: &[[u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS],
+    signer_count: usize,
+) -> Result<Option<(u32, [u8; BLAKE2B_HASH_SIZE])>, ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    let mut found = None;
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_CELL_DEP)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len >= 33 && cell_type_of(prefix[0]) == TURNOUT_ATTESTATION_TYPE && bytes_equal(&prefix[1..33], event_id) {
+            if found.is_some() {
+                return Err(ContractError::AmbiguousAttestation);
+            }
+            found = Some(i);
+        }
+    }
+    
+    let attestation_index = match found {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let mut buf = [0u8; MAX_ATTESTATION_SIZE];
+    let attestation_len = load_cell_data(&mut buf, attestation_index, SOURCE_CELL_DEP)?;
+    
+    // Format: [body: 69][sig_count: u8][[pubkey][sig: 64]]...
+    if attestation_len < ATTESTATION_BODY_SIZE + 1 || attestation_len > buf.len() {
+        return Err(ContractError::Encoding);
+    }
+    
+    let voter_count = u32::from_le_bytes([
+        buf[ATTESTATION_VOTER_COUNT_OFFSET],
+        buf[ATTESTATION_VOTER_COUNT_OFFSET + 1],
+        buf[ATTESTATION_VOTER_COUNT_OFFSET + 2],
+        buf[ATTESTATION_VOTER_COUNT_OFFSET + 3],
+    ]);
+    
+    const TAG_LEN: usize = DOMAIN_ATTEST.len();
+    let mut preimage = [0u8; TAG_LEN + ATTESTATION_BODY_SIZE];
+    preimage[..TAG_LEN].copy_from_slice(DOMAIN_ATTEST);
+    preimage[TAG_LEN..].copy_from_slice(&buf[..ATTESTATION_BODY_SIZE]);
+    let mut message_hash = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage, &mut message_hash);
+    
+    let sig_count = buf[ATTESTATION_BODY_SIZE] as usize;
+    let entry_size = signer_entry_size(metadata.signature_scheme);
+    if ATTESTATION_BODY_SIZE + 1 + sig_count * entry_size > attestation_len {
+        return Err(ContractError::Encoding);
+    }
+    
+    let mut seen = [[0u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS];
+    let mut seen_count = 0usize;
+    
+    for i in 0..sig_count {
+        let offset = ATTESTATION_BODY_SIZE + 1 + i * entry_size;
+        let entry = &buf[offset..offset + entry_size];
+        
+        let computed_hash = signer_entry_hash(entry, metadata.signature_scheme);
+        
+        if !is_authorized_signer(&computed_hash, signers, signer_count) {
+            return Err(ContractError::InvalidSignature);
+        }
+        
+        if !mark_signer_seen(&mut seen, &mut seen_count, &computed_hash) {
+            return Err(ContractError::InvalidSignature);
+        }
+        
+        if !verify_signer_entry(entry, metadata.signature_scheme, &message_hash) {
+            return Err(ContractError::InvalidSignature);
+        }
+    }
+    
+    if sig_count < metadata.required_signatures as usize {
+        return Ok(None);
+    }
+    
+    let mut ballot_root = [0u8; BLAKE2B_HASH_SIZE];
+    ballot_root.copy_from_slice(&buf[ATTESTATION_BALLOT_ROOT_OFFSET..ATTESTATION_BODY_SIZE]);
+    
+    Ok(Some((voter_count, ballot_root)))
+}
+
+/// Compute the root a turnout attestation commits to for the input ballots
+///
+/// A hash chain over each voter's latest ballot in input order, starting
+/// from zero: node = blake2b(node || blake2b(encrypted ballot)). Checking
+/// it ties the attested turnout to the ballots actually tallied.
+fn compute_ballot_root(event_id: &[u8], payload_size: usize) -> Result<[u8; BLAKE2B_HASH_SIZE], ContractError> {
+    let mut node = [0u8; 2 * BLAKE2B_HASH_SIZE];
+    let mut root = [0u8; BLAKE2B_HASH_SIZE];
+    let mut runs = VoterRuns::new(event_id);
+    let mut buf = [0u8; 512];
+    let payload_end = BALLOT_PAYLOAD_OFFSET + payload_size;
+    
+    for i in 0.. {
+        let len = match scan_cell_data(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if !is_event_voter_cell(&buf, len, event_id)? || !runs.is_latest(i)? {
+            continue;
+        }
+        if len < payload_end || payload_end > buf.len() {
+            return Err(ContractError::Encoding);
+        }
+        
+        node[..BLAKE2B_HASH_SIZE].copy_from_slice(&root);
+        let mut ballot_hash = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&buf[BALLOT_PAYLOAD_OFFSET..payload_end], &mut ballot_hash);
+        node[BLAKE2B_HASH_SIZE..].copy_from_slice(&ballot_hash);
+        blake2b_hash(&node, &mut root);
+    }
+    
+    Ok(root)
+}
+
+/// Find the output voter cell for a voter, loading its data into buf
+///
+/// Returns the output index and data length, or None if the transaction has
+/// no ballot for the voter. In a batch of ballots the voter's authorization
+/// is the witness at the same index as their ballot.
+fn find_output_ballot(event_id: &[u8], voter_hash: &[u8], buf: &mut [u8]) -> Result<Option<(usize, usize)>, ContractError> {
+    for i in 0.. {
+        let len = match scan_cell_data(buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len >= BALLOT_PAYLOAD_OFFSET
+            && is_event_voter_cell(buf, len, event_id)?
+            && bytes_equal(&buf[33..53], voter_hash)
+        {
+            return Ok(Some((i, len)));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Check whether an input is locked by the organizer's own lock
+///
+/// Compares the full lock script hash against the one the metadata names
+/// with METADATA_EXT_ORGANIZER_LOCK. Requiring one of their cells as an
+/// input means the organizer's lock must also approve the transaction,
+/// independently of the signature carried in this script's witness.
+fn has_organizer_input(organizer_lock_script_hash: &[u8; BLAKE2B_HASH_SIZE]) -> Result<bool, ContractError> {
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    
+    for i in 0.. {
+        if scan_cell_by_field(&mut lock_hash, i, SOURCE_INPUT, CELL_FIELD_LOCK_HASH)?.is_none() {
+            break;
+        }
+        
+        if bytes_equal_ct(&lock_hash, organizer_lock_script_hash) {
+            return Ok(true);
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Check whether any cell dep's data hashes to the given value
+fn has_cell_dep_with_data_hash(data_hash: &[u8; BLAKE2B_HASH_SIZE]) -> Result<bool, ContractError> {
+    let mut hash = [0u8; BLAKE2B_HASH_SIZE];
+    
+    for i in 0.. {
+        if scan_cell_by_field(&mut hash, i, SOURCE_CELL_DEP, CELL_FIELD_DATA_HASH)?.is_none() {
+            break;
+        }
+        
+        if bytes_equal_ct(&hash, data_hash) {
+            return Ok(true);
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Count the output voter cells for an event and sum their capacity
+///
+/// A batch may carry ballots for up to MAX_BATCH_BALLOTS voters, each at
+/// most once. The fund pays for every one, and no lock runs when they are
+/// created, so each is checked here: it must be under its voter's
+/// VoteSecure lock, signed by that voter in the witness at its output
+/// index, and hold exactly the deposit (or its occupied capacity, when
+/// larger) so no extra capacity can leave the fund through it.
+fn sum_output_ballots(event_id: &[u8], metadata: &ParsedMetadata) -> Result<(u32, u64), ContractError> {
+    let own_code = load_own_script_code()?;
+    let allow_delegation = metadata.flags & FLAG_DELEGATION != 0;
+    
+    let mut voters = [[0u8; PUBKEY_HASH_SIZE]; MAX_BATCH_BALLOTS];
+    let mut count = 0usize;
+    let mut total_capacity = 0u64;
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    let mut sequence = [0u8; 4];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if !is_event_voter_cell(&buf, len, event_id)? {
+            continue;
+        }
+        if count == MAX_BATCH_BALLOTS {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        let voter_hash = &buf[33..53];
+        for voter in voters.iter().take(count) {
+            if bytes_equal(voter, voter_hash) {
+                return Err(ContractError::EventfundMisuse);
+            }
+        }
+        voters[count].copy_from_slice(voter_hash);
+        count += 1;
+        
+        if !has_vote_secure_lock(i, SOURCE_OUTPUT, &own_code, VOTER_TYPE, event_id, Some(voter_hash))? {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        if len < BALLOT_SEQUENCE_OFFSET + sequence.len() {
+            return Err(ContractError::Encoding);
+        }
+        load_cell_data_at(&mut sequence, BALLOT_SEQUENCE_OFFSET, i, SOURCE_OUTPUT)?;
+        verify_voter_signature(event_id, voter_hash, u32::from_le_bytes(sequence), i, allow_delegation)?;
+        
+        let ballot_capacity = load_capacity(i, SOURCE_OUTPUT)?;
+        if ballot_capacity < metadata.min_ballot_deposit {
+            return Err(ContractError::InsufficientDeposit);
+        }
+        if ballot_capacity != metadata.min_ballot_deposit.max(load_occupied_capacity(i, SOURCE_OUTPUT)?) {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        total_capacity = capacity::checked_sum([total_capacity, ballot_capacity])?;
+    }
+    
+    Ok((count as u32, total_capacity))
+}
+
+/// Check every output ballot is paid by an EventFund input of this group
+///
+/// With FLAG_FUND_BINDING each voter cell names the input that funded it.
+/// Each fund input must hold one ballot fee plus the ballot's capacity for
+/// every ballot naming it, and all `ballot_count` ballots must name one of
+/// this group's inputs, so no fund spend can be claimed twice.
+fn verify_fund_claims(event_id: &[u8], metadata: &ParsedMetadata, ballot_count: u32) -> Result<(), ContractError> {
+    let own_lock_hash = load_group_lock_hash()?;
+    let ref_offset = ballot_fund_ref_offset(metadata);
+    
+    let mut claimed = 0u32;
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        if !prefix_matches(&prefix, len, EVENTFUND_TYPE, event_id, None) {
+            continue;
+        }
+        load_cell_by_field(&mut lock_hash, i, SOURCE_INPUT, CELL_FIELD_LOCK_HASH)?;
+        if !bytes_equal(&lock_hash, &own_lock_hash) {
+            continue;
+        }
+        
+        let (claims, claims_capacity) = sum_fund_claims(event_id, i, ref_offset)?;
+        let fees = capacity::checked_fees(metadata.ballot_fee, claims as u64)?;
+        let required = capacity::checked_sum([fees, claims_capacity])?;
+        if required > load_capacity(i, SOURCE_INPUT)? {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        claimed += claims;
+    }
+    
+    if claimed != ballot_count {
+        return Err(ContractError::EventfundMisuse);
+    }
+    
+    Ok(())
+}
+
+/// Count the output ballots naming `fund_index` and sum their capacity
+fn sum_fund_claims(event_id: &[u8], fund_index: usize, ref_offset: usize) -> Result<(u32, u64), ContractError> {
+    let mut count = 0u32;
+    let mut total_capacity = 0u64;
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    let mut fund_ref = [0u8; FUND_REF_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        if !is_event_voter_cell(&prefix, len, event_id)? {
+            continue;
+        }
+        
+        if len < ref_offset + FUND_REF_SIZE {
+            return Err(ContractError::Encoding);
+        }
+        load_cell_data_at(&mut fund_ref, ref_offset, i, SOURCE_OUTPUT)?;
+        if u32::from_le_bytes(fund_ref) as usize != fund_index {
+            continue;
+        }
+        
+        count += 1;
+        total_capacity = capacity::checked_sum([total_capacity, load_capacity(i, SOURCE_OUTPUT)?])?;
+    }
+    
+    Ok((count, total_capacity))
+}
+
+/// Count the EventFund outputs for an event and sum their capacity
+///
+/// Only outputs this lock still guards are counted.
+fn sum_output_eventfunds(event_id: &[u8]) -> Result<(u32, u64), ContractError> {
+    let own_lock_hash = load_group_lock_hash()?;
+    
+    let mut count = 0u32;
+    let mut total_capacity = 0u64;
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if !prefix_matches(&buf, len, EVENTFUND_TYPE, event_id, None) {
+            continue;
+        }
+        load_cell_by_field(&mut lock_hash, i, SOURCE_OUTPUT, CELL_FIELD_LOCK_HASH)?;
+        if !bytes_equal(&lock_hash, &own_lock_hash) {
+            continue;
+        }
+        
+        count += 1;
+        total_capacity = capacity::checked_sum([total_capacity, load_capacity(i, SOURCE_OUTPUT)?])?;
+    }
+    
+    Ok((count, total_capacity))
+}
+
+/// Sum the capacity of every input in the script group
+///
+/// A fund split into several cells may be spent together, so accounting
+/// must cover all of them rather than just the first.
+fn sum_group_input_capacity() -> Result<u64, ContractError> {
+    let mut total_capacity = 0u64;
+    let mut buf = [0u8; 8];
+    
+    for i in 0.. {
+        if scan_cell_by_field(&mut buf, i, SOURCE_GROUP_INPUT, CELL_FIELD_CAPACITY)?.is_none() {
+            break;
+        }
+        
+        total_capacity = capacity::checked_sum([total_capacity, u64::from_le_bytes(buf)])?;
+    }
+    
+    Ok(total_capacity)
+}
+
+/// Load the lock hash shared by every cell in this script group
+fn load_group_lock_hash() -> Result<[u8; BLAKE2B_HASH_SIZE], ContractError> {
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    load_cell_by_field(&mut lock_hash, 0, SOURCE_GROUP_INPUT, CELL_FIELD_LOCK_HASH)?;
+    Ok(lock_hash)
+}
+
+/// Load the code the running script runs
+fn load_own_script_code() -> Result<ScriptCode, ContractError> {
+    let mut script_buf = [0u8; MAX_SCRIPT_SIZE];
+    let script_len = load_script(&mut script_buf)?;
+    if script_len > script_buf.len() {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    let (code, _) = parse_script(&script_buf[..script_len])?;
+    Ok(code)
+}
+
+/// Check a cell is locked by VoteSecure with the given args
+///
+/// The lock must run `own_code` and its args must name the cell type, the
+/// event and, when given, the voter. Cells of other VoteSecure events, or
+/// any other lock, don't match.
+fn has_vote_secure_lock(
+    index: usize,
+    source: u64,
+    own_code: &ScriptCode,
+    cell_type: u8,
+    event_id: &[u8],
+    voter_hash: Option<&[u8]>,
+) -> Result<bool, ContractError> {
+    let mut script_buf = [0u8; MAX_SCRIPT_SIZE];
+    let script_len = load_cell_by_field(&mut script_buf, index, source, CELL_FIELD_LOCK)?;
+    if script_len > script_buf.len() {
+        return Ok(false);
+    }
+    
+    let (code, args_bytes) = match parse_script(&script_buf[..script_len]) {
+        Ok(parsed) => parsed,
+        Err(_) => return Ok(false),
+    };
+    if code != *own_code {
+        return Ok(false);
+    }
+    let args = match parse_script_args(args_bytes) {
+        Ok(args) => args,
+        Err(_) => return Ok(false),
+    };
+    
+    if args.cell_type != cell_type || !bytes_equal(&args.event_id, event_id) {
+        return Ok(false);
+    }
+    match voter_hash {
+        Some(voter_hash) => Ok(args.has_voter_hash && bytes_equal(&args.voter_hash, voter_hash)),
+        None => Ok(true),
+    }
+}
+
+/// Whether a loaded cell prefix is a voter cell for the event
+///
+/// A voter cell of a version this script can't read is an encoding error
+/// rather than a non-match, so it is never silently left out of a count.
+fn is_event_voter_cell(buf: &[u8], len: usize, event_id: &[u8]) -> Result<bool, ContractError> {
+    if len < CELL_PREFIX_SIZE || cell_type_of(buf[0]) != VOTER_TYPE || !bytes_equal(&buf[1..33], event_id) {
+        return Ok(false);
+    }
+    
+    match buf[0] >> 4 {
+        VOTER_VERSION_0 => Ok(true),
+        _ => Err(ContractError::Encoding),
+    }
+}
+
+/// Check no input or output is a VoteSecure cell of another event
+///
+/// Funding cells carry no data, so any cell at least as long as the type
+/// tag and event_id whose tag is a VoteSecure cell type is taken to be one.
+/// Mixing events in one transaction is never needed, and refusing it keeps
+/// one event's accounting from being confused by another's cells.
+fn verify_no_foreign_cells(event_id: &[u8]) -> Result<(), ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    
+    for source in [SOURCE_INPUT, SOURCE_OUTPUT] {
+        for i in 0.. {
+            let len = match load_cell_prefix(&mut prefix, i, source)? {
+                Some(len) => len,
+                None => break,
+            };
+            if len < 33 {
+                continue;
+            }
+            
+            let recognized = matches!(
+                cell_type_of(prefix[0]),
+                EVENTFUND_TYPE | METADATA_TYPE | VOTER_TYPE | RESULT_TYPE | AUDITLOG_TYPE | REGISTRATION_TYPE
+            );
+            if recognized && !bytes_equal(&prefix[1..33], event_id) {
+                return Err(ContractError::InvalidArgs);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// The cell type named by a data tag
+///
+/// Voter tags carry a layout version in the high nibble; every other tag
+/// is the type itself. All type comparisons go through this.
+fn cell_type_of(tag: u8) -> u8 {
+    if tag & CELL_TYPE_MASK == VOTER_TYPE {
+        VOTER_TYPE
+    } else {
+        tag
+    }
+}
+
+/// Check a loaded cell prefix carries the given type, event and voter
+///
+/// Voter cells only match in a version this script can read.
+fn prefix_matches(
+    prefix: &[u8; CELL_PREFIX_SIZE],
+    len: usize,
+    cell_type: u8,
+    event_id: &[u8],
+    voter_hash: Option<&[u8]>,
+) -> bool {
+    if len < 33 || cell_type_of(prefix[0]) != cell_type || !bytes_equal(&prefix[1..33], event_id) {
+        return false;
+    }
+    if cell_type == VOTER_TYPE && prefix[0] >> 4 != VOTER_VERSION_0 {
+        return false;
+    }
+    match voter_hash {
+        Some(voter_hash) => len >= CELL_PREFIX_SIZE && bytes_equal(&prefix[33..53], voter_hash),
+        None => true,
+    }
+}
+
+/// Check every input this script instance guards matches its lock args
+///
+/// Operations load their own cell as group input 0, so a second group
+/// input with foreign data would otherwise be spent unexamined.
+fn verify_group_binding(cell_type: u8, event_id: &[u8], voter_hash: Option<&[u8]>) -> Result<(), ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_GROUP_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        // A voter's registration cell is held under the voter's lock
+        if cell_type == VOTER_TYPE && prefix_matches(&prefix, len, REGISTRATION_TYPE, event_id, voter_hash) {
+            continue;
+        }
+        
+        if !prefix_matches(&prefix, len, cell_type, event_id, voter_hash) {
+            return Err(ContractError::InvalidArgs);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Check every input ballot embedding this voter and event is under our lock
+///
+/// Revote limits and sequence numbers count input voter cells by their
+/// embedded event_id and voter hash. A cell embedding them under another
+/// lock would be counted as the voter's prior ballot, so the counted cells
+/// must be the group's own, which `verify_group_binding` has matched to
+/// the args.
+fn verify_prior_ballot_binding(event_id: &[u8], voter_hash: &[u8]) -> Result<(), ContractError> {
+    let own_lock_hash = load_group_lock_hash()?;
+    
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        if !prefix_matches(&prefix, len, VOTER_TYPE, event_id, Some(voter_hash)) {
+            continue;
+        }
+        
+        load_cell_by_field(&mut lock_hash, i, SOURCE_INPUT, CELL_FIELD_LOCK_HASH)?;
+        if !bytes_equal(&lock_hash, &own_lock_hash) {
+            return Err(ContractError::InvalidArgs);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Check every output locked by this script embeds the same type and event
+///
+/// Cell data repeats the type, event_id and (for voter cells) the
+/// voter_pubkey_hash from the lock args. A successor cell whose data
+/// disagrees with its lock would be attributed to the wrong event or voter
+/// by anything reading the data.
+fn verify_output_binding(cell_type: u8, event_id: &[u8], voter_hash: Option<&[u8]>) -> Result<(), ContractError> {
+    let own_lock_hash = load_group_lock_hash()?;
+    
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    for i in 0.. {
+        if scan_cell_by_field(&mut lock_hash, i, SOURCE_OUTPUT, CELL_FIELD_LOCK_HASH)?.is_none() {
+            break;
+        }
+        if !bytes_equal(&lock_hash, &own_lock_hash) {
+            continue;
+        }
+        
+        let len = load_cell_prefix(&mut prefix, i, SOURCE_OUTPUT)?.unwrap_or(0);
+        if !prefix_matches(&prefix, len, cell_type, event_id, voter_hash) {
+            return Err(ContractError::InvalidArgs);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Read the sequence number of a loaded voter cell
+fn ballot_sequence_number(ballot_data: &[u8]) -> u32 {
+    u32::from_le_bytes([
+        ballot_data[BALLOT_SEQUENCE_OFFSET],
+        ballot_data[BALLOT_SEQUENCE_OFFSET + 1],
+        ballot_data[BALLOT_SEQUENCE_OFFSET + 2],
+        ballot_data[BALLOT_SEQUENCE_OFFSET + 3],
+    ])
+}
+
+/// Read the submission timestamp of a loaded voter cell
+fn ballot_timestamp(ballot_data: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&ballot_data[BALLOT_TIMESTAMP_OFFSET..BALLOT_TIMESTAMP_OFFSET + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Compute the message signed for an operation on the transaction
+///
+/// Message = blake2b(tag || tx_hash), with `tag` one of the DOMAIN_ tags.
+fn domain_message(tag: &[u8], tx_hash: &[u8; 32]) -> [u8; 32] {
+    let mut preimage = [0u8; MAX_DOMAIN_TAG_SIZE + BLAKE2B_HASH_SIZE];
+    let preimage_len = tag.len() + BLAKE2B_HASH_SIZE;
+    preimage[..tag.len()].copy_from_slice(tag);
+    preimage[tag.len()..preimage_len].copy_from_slice(tx_hash);
+    
+    let mut message = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage[..preimage_len], &mut message);
+    message
+}
+
+/// Compute the message a voter signs for a ballot
+///
+/// Message = blake2b(DOMAIN_BALLOT || tx_hash || event_id || sequence_number
+/// as u32 LE). The event id stops a signature collected for one event from
+/// authorizing a ballot in another, and the sequence number means every
+/// revote needs a fresh signature.
+fn ballot_signing_message(tx_hash: &[u8; 32], event_id: &[u8], sequence_number: u32) -> [u8; 32] {
+    const TAG_LEN: usize = DOMAIN_BALLOT.len();
+    let mut preimage = [0u8; TAG_LEN + BLAKE2B_HASH_SIZE + EVENT_ID_SIZE + 4];
+    preimage[..TAG_LEN].copy_from_slice(DOMAIN_BALLOT);
+    preimage[TAG_LEN..TAG_LEN + 32].copy_from_slice(tx_hash);
+    preimage[TAG_LEN + 32..TAG_LEN + 64].copy_from_slice(event_id);
+    preimage[TAG_LEN + 64..].copy_from_slice(&sequence_number.to_le_bytes());
+    
+    let mut message = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage, &mut message);
+    message
+}
+
+/// Message a voter signs to let a proxy cast their ballots for an event
+///
+/// Message = blake2b(DOMAIN_DELEGATE || event_id || voter_hash || proxy_hash).
+fn delegation_message(event_id: &[u8], voter_hash: &[u8], proxy_hash: &[u8; PUBKEY_HASH_SIZE]) -> [u8; 32] {
+    const TAG_LEN: usize = DOMAIN_DELEGATE.len();
+    let mut preimage = [0u8; TAG_LEN + EVENT_ID_SIZE + 2 * PUBKEY_HASH_SIZE];
+    preimage[..TAG_LEN].copy_from_slice(DOMAIN_DELEGATE);
+    preimage[TAG_LEN..TAG_LEN + EVENT_ID_SIZE].copy_from_slice(event_id);
+    preimage[TAG_LEN + EVENT_ID_SIZE..TAG_LEN + EVENT_ID_SIZE + PUBKEY_HASH_SIZE].copy_from_slice(voter_hash);
+    preimage[TAG_LEN + EVENT_ID_SIZE + PUBKEY_HASH_SIZE..].copy_from_slice(proxy_hash);
+    
+    let mut message = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage, &mut message);
+    message
+}
+
+/// Message signed by invite approvals: binds the invite to one voter
+///
+/// Message = blake2b(DOMAIN_INVITE_BLS || event_id || voter_hash).
+fn invite_message(event_id: &[u8], voter_hash: &[u8]) -> [u8; 32] {
+    const TAG_LEN: usize = DOMAIN_INVITE_BLS.len();
+    let mut preimage = [0u8; TAG_LEN + EVENT_ID_SIZE + PUBKEY_HASH_SIZE];
+    preimage[..TAG_LEN].copy_from_slice(DOMAIN_INVITE_BLS);
+    preimage[TAG_LEN..TAG_LEN + EVENT_ID_SIZE].copy_from_slice(event_id);
+    preimage[TAG_LEN + EVENT_ID_SIZE..].copy_from_slice(voter_hash);
+    
+    let mut message = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage, &mut message);
+    message
+}
+
+/// Verify the audit chain link carried by a successor cell
+///
+/// With the audit chain enabled, every state-changing output ends with a
+/// 32-byte prev_state_hash that must equal the blake2b hash of the data of
+/// the cell it replaces (group input 0), so auditors can walk the event's
+/// history backwards from any cell.
+fn verify_chain_link(successor_len: usize, successor_data: &[u8]) -> Result<(), ContractError> {
+    // The link is the trailing field, so the whole cell must have been loaded
+    if successor_len > successor_data.len() {
+        return Err(ContractError::Encoding);
+    }
+    if successor_len < BLAKE2B_HASH_SIZE {
+        return Err(ContractError::BrokenChain);
+    }
+    
+    // A predecessor too long to hash here can't be linked to
+    let mut prev_buf = [0u8; MAX_CHAINED_CELL_SIZE];
+    let prev_len = load_cell_data(&mut prev_buf, 0, SOURCE_GROUP_INPUT)?;
+    if prev_len > prev_buf.len() {
+        return Err(ContractError::BrokenChain);
+    }
+    
+    let mut prev_hash = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&prev_buf[..prev_len], &mut prev_hash);
+    
+    let link = &successor_data[successor_len - BLAKE2B_HASH_SIZE..successor_len];
+    if !bytes_equal_ct(&prev_hash, link) {
+        return Err(ContractError::BrokenChain);
+    }
+    
+    Ok(())
+}
+
+/// Compute transaction hash for signature verification
+///
+/// Returns the blake2b hash of the serialized transaction (without witnesses)
+/// as computed by the CKB VM, which every signature in this contract must
+/// commit to behind its operation's domain tag.
+fn compute_tx_hash() -> Result<[u8; 32], ContractError> {
+    let mut hash = [0u8; 32];
+    load_tx_hash(&mut hash)?;
+    Ok(hash)
+}
+
+// ============================================================================
+// Validation Functions
+// ============================================================================
+
+/// Verify EventFund cell spending
+fn verify_eventfund(event_id: &[u8]) -> Result<(), ContractError> {
+    // Load metadata to get organizer info
+    let metadata = load_metadata(event_id)?;
+    
+    // An emergency halt blocks every operation on the event
+    if metadata.flags & FLAG_EMERGENCY_HALT != 0 {
+        return Err(ContractError::EmergencyHalt);
+    }
+    
+    // So does a signer pause while a dispute is resolved
+    if metadata.paused != 0 {
+        return Err(ContractError::EventPaused);
+    }
+    
+    // Strict events refuse transactions mixing in another event's cells
+    if metadata.flags & FLAG_STRICT_TX != 0 {
+        verify_no_foreign_cells(event_id)?;
+    }
+    
+    // The fund must belong to the organizer the metadata names
+    verify_fund_organizer(event_id, &metadata.organizer_lock_hash)?;
+    
+    // Get the current time on the event's schedule basis
+    let current_time = load_schedule_time(&metadata)?;
+    
+    let voting_close = submission_close(&metadata)?;
+    
+    // Check if this is ballot payment (during voting) or final withdrawal
+    let is_voting_period = current_time >= metadata.voting_start 
+        && current_time <= voting_close;
+    let is_audit_period = current_time > voting_close
+        && current_time < metadata.audit_end_time;
+    let is_after_audit = current_time >= metadata.audit_end_time;
+    
+    if current_time < metadata.voting_start {
+        // Before voting: organizer may top up the fund or split it into
+        // several cells so concurrent ballots don't contend for one cell
+        return verify_eventfund_setup(event_id, &metadata);
+    }
+    
+    if is_voting_period {
+        // During voting: EventFund can be spent to pay for ballot submission,
+        // possibly for a batch of distinct voters in one transaction
+        let (ballot_count, ballot_capacity) = sum_output_ballots(event_id, &metadata)?;
+        if ballot_count == 0 {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        // The remaining funds must return to change EventFund cells that
+        // this lock still guards; a batch may split its change over several
+        let (change_count, change_capacity) = sum_output_eventfunds(event_id)?;
+        if change_count == 0 {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        // CAPACITY ACCOUNTING: the fund may only shrink by one ballot fee
+        // per ballot plus the capacity locked into the new Voter cells
+        let input_capacity = sum_group_input_capacity()?;
+        let spent = capacity::checked_sub(input_capacity, change_capacity)?;
+        let fees = capacity::checked_fees(metadata.ballot_fee, ballot_count as u64)?;
+        let allowed = capacity::checked_sum([fees, ballot_capacity])?;
+        
+        if spent != allowed {
+            return Err(ContractError::EventfundMisuse);
+        }
+        
+        // FUND BINDING: the total balances, now check it per fund input
+        if metadata.flags & FLAG_FUND_BINDING != 0 {
+            verify_fund_claims(event_id, &metadata, ballot_count)?;
+        }
+        
+        return Ok(());
+    }
+    
+    if is_audit_period {
+        // During audit: the fund is frozen so ballots can't be paid for and
+        // the organizer can't withdraw while results may still be disputed
+        return Err(ContractError::FundLockedAudit);
+    }
+    
+    if is_after_audit {
+        // RECOVERY: a withdrawal signed by the recovery key is it sweeping
+        // a fund its organizer can no longer reach
+        if metadata.recovery_signer_hash != [0u8; PUBKEY_HASH_SIZE]
+            && witness_signer_is(&metadata.recovery_signer_hash)
+        {
+            verify_no_late_ballots(event_id, &metadata, current_time)?;
+            return verify_fund_recovery(&metadata, current_time);
+        }
+        
+        // After audit period: organizer can withdraw remaining funds, but
+        // only once the results they were paid to run are on chain
+        if !has_finalized_result(event_id)? {
+            return Err(ContractError::ResultsNotFinalized);
+        }
+        verify_no_late_ballots(event_id, &metadata, current_time)?;
+        return verify_organizer_spend(&metadata, DOMAIN_WITHDRAW);
+    }
+    
+    // Outside valid periods: cannot spend EventFund
+    Err(ContractError::InvalidTiming)
+}
+
+/// Verify a pre-voting setup of the EventFund
+///
+/// Setup is organizer-signed and either splits the fund into at least two
+/// EventFund cells for the same event with capacity preserved, or funds it
+/// further. No capacity may leave the fund either way.
+///
+/// Lock scripts don't run when a cell is created, so setup is where the
+/// fund is checked against the declared capacity: the EventFund outputs
+/// must cover `max_participants` ballots at `ballot_fee` each.
+fn verify_eventfund_setup(event_id: &[u8], metadata: &ParsedMetadata) -> Result<(), ContractError> {
+    verify_organizer_spend(metadata, DOMAIN_WITHDRAW)?;
+    
+    let input_capacity = sum_group_input_capacity()?;
+    let (fund_count, fund_capacity) = sum_output_eventfunds(event_id)?;
+    
+    // Outputs with the wrong type byte, event_id or lock are not counted, so
+    // any capacity sent to them shows up as a shortfall here
+    if fund_count == 0 || fund_capacity < input_capacity {
+        return Err(ContractError::EventfundMisuse);
+    }
+    
+    // A setup that adds no capacity must be a split
+    if fund_capacity == input_capacity && fund_count < 2 {
+        return Err(ContractError::EventfundMisuse);
+    }
+    
+    let required = capacity::checked_fees(metadata.ballot_fee, metadata.max_participants as u64)?;
+    if fund_capacity < required {
+        return Err(ContractError::EventfundMisuse);
+    }
+    
+    Ok(())
+}
+
+/// Check every EventFund cell spent or created names the event's organizer
+///
+/// Covers the group inputs and the event's EventFund outputs, so change and
+/// split cells can't be handed to a different organizer either.
+fn verify_fund_organizer(event_id: &[u8], organizer_lock_hash: &[u8; PUBKEY_HASH_SIZE]) -> Result<(), ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    
+    for source in [SOURCE_GROUP_INPUT, SOURCE_OUTPUT] {
+        for i in 0.. {
+            let len = match load_cell_prefix(&mut prefix, i, source)? {
+                Some(len) => len,
+                None => break,
+            };
+            if !prefix_matches(&prefix, len, EVENTFUND_TYPE, event_id, None) {
+                continue;
+            }
+            
+            if len < CELL_PREFIX_SIZE || !bytes_equal(&prefix[33..53], organizer_lock_hash) {
+                return Err(ContractError::UnauthorizedWithdrawal);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Verify the recovery key sweeping the fund (FUND_RECOVER)
+///
+/// Only possible once recovery_unlock_time, set well past the audit
+/// period, has passed. No result is required: a fund stranded by a lost
+/// organizer key may belong to an event that never released one.
+fn verify_fund_recovery(metadata: &ParsedMetadata, current_time: u64) -> Result<(), ContractError> {
+    if current_time < metadata.recovery_unlock_time {
+        return Err(ContractError::TimelockNotExpired);
+    }
+    
+    let mut witness_buf = [0u8; 256];
+    if let Err(e) = load_witness_lock(&mut witness_buf, 0) {
+        return Err(witness_error(e, ContractError::UnauthorizedWithdrawal));
+    }
+    
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(DOMAIN_RECOVER, &tx_hash);
+    
+    if let Err(failure) = verify_signature_by_hash(&metadata.recovery_signer_hash, &witness_buf, &message) {
+        return Err(signature_error(failure, ContractError::UnauthorizedWithdrawal));
+    }
+    
+    Ok(())
+}
+
+/// Whether the first witness is signed with the key hashing to `signer_hash`
+///
+/// Only picks which authorization path applies; the signature itself is
+/// verified by that path.
+fn witness_signer_is(signer_hash: &[u8; PUBKEY_HASH_SIZE]) -> bool {
+    let mut witness_buf = [0u8; 256];
+    let matches = match load_witness_lock(&mut witness_buf, 0) {
+        Ok(len) if len >= SIGNER_ENTRY_SIZE => {
+            bytes_equal(&signer_entry_hash(&witness_buf[..SIGNER_ENTRY_SIZE], SIGNATURE_SCHEME_ECDSA), signer_hash)
+        }
+        _ => false,
+    };
+    secure_zero(&mut witness_buf);
+    
+    matches
+}
+
+/// Require the organizer's signature over the tx under the given domain
+///
+/// The signature is checked against `organizer_lock_hash`, the organizer's
+/// pubkey hash. When the metadata also names the organizer's lock script,
+/// one of their cells must be an input too.
+fn verify_organizer_spend(metadata: &ParsedMetadata, domain_tag: &[u8]) -> Result<(), ContractError> {
+    if metadata.organizer_lock_script_hash != [0u8; BLAKE2B_HASH_SIZE]
+        && !has_organizer_input(&metadata.organizer_lock_script_hash)?
+    {
+        return Err(ContractError::UnauthorizedWithdrawal);
+    }
+    
+    // Verify organizer signature
+    let mut witness_buf = [0u8; 256];
+    if let Err(e) = load_witness_lock(&mut witness_buf, 0) {
+        return Err(witness_error(e, ContractError::UnauthorizedWithdrawal));
+    }
+    
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(domain_tag, &tx_hash);
+    
+    if let Err(failure) = verify_signature_by_hash(
+        &metadata.organizer_lock_hash,
+        &witness_buf,
+        &message,
+    ) {
+        return Err(signature_error(failure, ContractError::UnauthorizedWithdrawal));
+    }
+    
+    Ok(())
+}
+
+/// Verify metadata cell operations
+fn verify_metadata(event_id: &[u8]) -> Result<(), ContractError> {
+    // Load metadata
+    let metadata = load_metadata(event_id)?;
+    
+    // Get the current time on the event's schedule basis
+    let current_time = load_schedule_time(&metadata)?;
+    
+    if metadata.flags & FLAG_STRICT_TX != 0 {
+        verify_no_foreign_cells(event_id)?;
+    }
+    
+    // A successor metadata cell means the metadata is being updated in place
+    if find_output_index(METADATA_TYPE, event_id)?.is_some() {
+        return verify_metadata_update(event_id, &metadata, current_time);
+    }
+    
+    if metadata.flags & FLAG_EMERGENCY_HALT != 0 {
+        return Err(ContractError::EmergencyHalt);
+    }
+    
+    if metadata.paused != 0 {
+        return Err(ContractError::EventPaused);
+    }
+    
+    // After audit period ends, allow metadata cleanup
+    if current_time >= metadata.audit_end_time {
+        verify_no_late_ballots(event_id, &metadata, current_time)?;
+        return verify_metadata_cleanup(&metadata);
+    }
+    
+    // Before audit period ends: metadata is immutable
+    Err(ContractError::MetadataImmutable)
+}
+
+/// Verify an in-place update of the metadata cell
+///
+/// Metadata is otherwise immutable, so the successor cell must be
+/// byte-identical to the consumed one apart from a single control field:
+/// the emergency halt bit, the paused byte, or audit_end_time.
+fn verify_metadata_update(event_id: &[u8], metadata: &ParsedMetadata, current_time: u64) -> Result<(), ContractError> {
+    let mut input_buf = [0u8; MAX_METADATA_SIZE];
+    let input_len = load_cell_data(&mut input_buf, 0, SOURCE_GROUP_INPUT)?;
+    if input_len > input_buf.len() {
+        return Err(ContractError::Encoding);
+    }
+    let current = parse_metadata(&input_buf[..input_len])?;
+    
+    let output_index = match find_output_index(METADATA_TYPE, event_id)? {
+        Some(i) => i,
+        None => return Err(ContractError::MetadataImmutable),
+    };
+    let mut output_buf = [0u8; MAX_METADATA_SIZE];
+    let output_len = load_cell_data(&mut output_buf, output_index, SOURCE_OUTPUT)?;
+    if output_len != input_len || input_len > input_buf.len() {
+        return Err(ContractError::MetadataImmutable);
+    }
+    
+    let input_data = &input_buf[..input_len];
+    let output_data = &output_buf[..output_len];
+    
+    if current.paused_offset != 0
+        && only_range_differs(input_data, output_data, current.paused_offset, current.paused_offset + 1)
+    {
+        return verify_pause_toggle(event_id, &current, output_data[current.paused_offset]);
+    }
+    
+    if current.flags_offset != 0
+        && only_range_differs(input_data, output_data, current.flags_offset, current.flags_offset + 4)
+    {
+        return verify_emergency_halt_toggle(metadata, &current, output_data);
+    }
+    
+    if only_range_differs(input_data, output_data, METADATA_AUDIT_END_OFFSET, METADATA_AUDIT_END_OFFSET + 8) {
+        return verify_audit_extension(event_id, &current, output_data, current_time);
+    }
+    
+    Err(ContractError::MetadataImmutable)
+}
+
+/// Check that two equal-length buffers match everywhere outside `start..end`
+fn only_range_differs(a: &[u8], b: &[u8], start: usize, end: usize) -> bool {
+    if a.len() != b.len() || end > a.len() {
+        return false;
+    }
+    bytes_equal(&a[..start], &b[..start]) && bytes_equal(&a[end..], &b[end..])
+}
+
+/// Verify a break-glass halt or resume of an event
+///
+/// The break-glass key may only flip FLAG_EMERGENCY_HALT. It cannot change
+/// anything else or move funds.
+fn verify_emergency_halt_toggle(metadata: &ParsedMetadata, current: &ParsedMetadata, output_data: &[u8]) -> Result<(), ContractError> {
+    if metadata.breakglass_hash == [0u8; PUBKEY_HASH_SIZE] {
+        return Err(ContractError::MetadataImmutable);
+    }
+    
+    let flags_start = current.flags_offset;
+    let new_flags = u32::from_le_bytes([
+        output_data[flags_start], output_data[flags_start + 1],
+        output_data[flags_start + 2], output_data[flags_start + 3],
+    ]);
+    if current.flags ^ new_flags != FLAG_EMERGENCY_HALT {
+        return Err(ContractError::MetadataImmutable);
+    }
+    
+    // Only the break-glass key can halt or resume
+    let mut witness_buf = [0u8; 256];
+    if let Err(e) = load_witness_lock(&mut witness_buf, 0) {
+        return Err(witness_error(e, ContractError::InvalidSignature));
+    }
+    
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(DOMAIN_HALT, &tx_hash);
+    
+    if let Err(failure) = verify_signature_by_hash(&metadata.breakglass_hash, &witness_buf, &message) {
+        return Err(signature_error(failure, ContractError::InvalidSignature));
+    }
+    
+    Ok(())
+}
+
+/// Verify a pause or unpause of an event
+///
+/// Pausing freezes ballots and result release pending dispute resolution.
+/// It needs the same signer quorum as a result release.
+fn verify_pause_toggle(event_id: &[u8], current: &ParsedMetadata, new_paused: u8) -> Result<(), ContractError> {
+    if new_paused > 1 || new_paused == current.paused {
+        return Err(ContractError::MetadataImmutable);
+    }
+    
+    verify_signer_quorum(event_id, current)
+}
+
+/// Verify an extension of the audit period
+///
+/// While a dispute is worked through, a signer quorum may push
+/// audit_end_time later to hold off withdrawal and cleanup. It can only
+/// move forward, and only before the current audit period has ended.
+fn verify_audit_extension(
+    event_id: &[u8],
+    current: &ParsedMetadata,
+    output_data: &[u8],
+    current_time: u64,
+) -> Result<(), ContractError> {
+    if current_time >= current.audit_end_time {
+        return Err(ContractError::MetadataImmutable);
+    }
+    
+    // The successor must itself be valid metadata with a later audit end
+    let updated = parse_metadata(output_data)?;
+    if updated.audit_end_time <= current.audit_end_time {
+        return Err(ContractError::MetadataImmutable);
+    }
+    
+    verify_signer_quorum(event_id, current)
+}
+
+/// Verify that a signer quorum approved a metadata update
+fn verify_signer_quorum(event_id: &[u8], current: &ParsedMetadata) -> Result<(), ContractError> {
+    let (lock_start, lock_len, sig_count) = load_multisig_header(0)?;
+    parse_signer_window(lock_len, sig_count, current.signature_scheme)?;
+    
+    // An event without a signer quorum can never be updated this way
+    if sig_count == 0 || sig_count < current.required_signatures {
+        return Err(ContractError::InsufficientSignatures);
+    }
+    
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(DOMAIN_UPDATE, &tx_hash);
+    
+    let mut signers = [[0u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS];
+    let signer_count = load_authorized_signers(event_id, &mut signers)?;
+    
+    verify_signer_signatures(lock_start, lock_len, sig_count, current.signature_scheme, &signers, signer_count, &message)
+}
+
+/// Verify metadata cleanup (consumption after audit period)
+fn verify_metadata_cleanup(metadata: &ParsedMetadata) -> Result<(), ContractError> {
+    verify_organizer_spend(metadata, DOMAIN_CLEANUP)
+}
+
+/// Verify the voter's signature over the ballot message
+///
+/// With delegation allowed, the witness may instead hold
+/// [proxy_pubkey][proxy_sig][voter_pubkey][delegation_sig]: the proxy signs
+/// the ballot and the voter signs the delegation to that proxy. The ballot
+/// still belongs to the voter in the lock args, so every count sees the
+/// voter and never the proxy.
+///
+/// The witness buffer is wiped before returning on every path once loaded.
+fn verify_voter_signature(
+    event_id: &[u8],
+    voter_hash: &[u8],
+    sequence_number: u32,
+    witness_index: usize,
+    allow_delegation: bool,
+) -> Result<(), ContractError> {
+    let mut witness_buf = [0u8; VOTER_WITNESS_BUFFER_SIZE];
+    let lock_len = load_voter_witness(&mut witness_buf, witness_index)?;
+    
+    let checked = check_voter_witness(event_id, voter_hash, sequence_number, &witness_buf, lock_len, allow_delegation);
+    secure_zero(&mut witness_buf);
+    
+    checked
+}
+
+/// Load the witness lock at `witness_index`, wiping the buffer on failure
+fn load_voter_witness(witness_buf: &mut [u8], witness_index: usize) -> Result<usize, ContractError> {
+    match load_witness_lock(witness_buf, witness_index) {
+        Ok(len) => Ok(len),
+        Err(e) => {
+            secure_zero(witness_buf);
+            Err(witness_error(e, ContractError::VoterIneligible))
+        }
+    }
+}
+
+/// Check the voter's signature in an already loaded witness lock
+///
+/// See `verify_voter_signature`; the caller owns and wipes the buffer.
+fn check_voter_witness(
+    event_id: &[u8],
+    voter_hash: &[u8],
+    sequence_number: u32,
+    witness_buf: &[u8],
+    lock_len: usize,
+    allow_delegation: bool,
+) -> Result<(), ContractError> {
+    let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
+        Ok(arr) => arr,
+        Err(_) => return Err(ContractError::VoterIneligible),
+    };
+    
+    let tx_hash = compute_tx_hash()?;
+    let ballot_message = ballot_signing_message(&tx_hash, event_id, sequence_number);
+    
+    let checked = if allow_delegation && lock_len == DELEGATED_WITNESS_SIZE {
+        let (proxy_entry, voter_entry) = witness_buf[..DELEGATED_WITNESS_SIZE].split_at(SIGNER_ENTRY_SIZE);
+        let proxy_hash = signer_entry_hash(proxy_entry, SIGNATURE_SCHEME_ECDSA);
+        
+        if verify_signer_entry(proxy_entry, SIGNATURE_SCHEME_ECDSA, &ballot_message) {
+            verify_signature_by_hash(
+                &voter_hash_array,
+                voter_entry,
+                &delegation_message(event_id, voter_hash, &proxy_hash),
+            )
+        } else {
+            Err(SignatureFailure::BadSignature)
+        }
+    } else {
+        verify_signature_by_hash(
+            &voter_hash_array,
+            witness_buf,
+            &ballot_message,
+        )
+    };
+    
+    if let Err(failure) = checked {
+        return Err(signature_error(failure, ContractError::InvalidSignature));
+    }
+    
+    Ok(())
+}
+
+/// Verify every audit log output for an event against the transaction
+///
+/// A log is only valid if the operation it names happens in the same
+/// transaction and was authorized by its actor: a ballot or revote by the
+/// voter (matched on sequence number), a release by an authorized signer,
+/// or a withdrawal by the organizer. Authorized means checked by a lock
+/// that runs in this transaction, not just an output naming the actor.
+/// The timestamp must be the operation's time. The actor's signature
+/// covers the tx hash, and with it the log.
+fn verify_output_audit_logs(event_id: &[u8]) -> Result<(), ContractError> {
+    let mut buf = [0u8; AUDITLOG_SIZE];
+    
+    for i in 0.. {
+        let len = match scan_cell_data(&mut buf, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len < 33 || cell_type_of(buf[0]) != AUDITLOG_TYPE || !bytes_equal(&buf[1..33], event_id) {
+            continue;
+        }
+        if len != AUDITLOG_SIZE {
+            return Err(ContractError::InvalidArgs);
+        }
+        
+        verify_audit_log(event_id, &buf)?;
+    }
+    
+    Ok(())
+}
+
+/// Cross-check one well-formed audit log with the operation it records
+fn verify_audit_log(event_id: &[u8], log: &[u8; AUDITLOG_SIZE]) -> Result<(), ContractError> {
+    let op_code = log[AUDITLOG_OP_OFFSET];
+    let mut timestamp_bytes = [0u8; 8];
+    timestamp_bytes.copy_from_slice(&log[AUDITLOG_TIMESTAMP_OFFSET..AUDITLOG_ACTOR_OFFSET]);
+    let actor_hash = &log[AUDITLOG_ACTOR_OFFSET..AUDITLOG_SIZE];
+    
+    if u64::from_le_bytes(timestamp_bytes) != load_current_timestamp()? {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    let metadata = load_metadata(event_id)?;
+    
+    let own_code = load_own_script_code()?;
+    let matches = match op_code {
+        AUDIT_OP_BALLOT | AUDIT_OP_REVOTE => {
+            let mut ballot_buf = [0u8; BALLOT_PAYLOAD_OFFSET];
+            let is_first = match find_output_ballot(event_id, actor_hash, &mut ballot_buf)? {
+                // A first ballot has sequence 0; anything later is a revote
+                Some(_) => ballot_sequence_number(&ballot_buf) == 0,
+                None => return Err(ContractError::InvalidArgs),
+            };
+            
+            // The voter's signature is checked by their own lock, or by the
+            // EventFund lock for every ballot the fund pays for
+            is_first == (op_code == AUDIT_OP_BALLOT)
+                && (has_vote_secure_input(&own_code, VOTER_TYPE, event_id, Some(actor_hash))?
+                    || has_vote_secure_input(&own_code, EVENTFUND_TYPE, event_id, None)?)
+        }
+        AUDIT_OP_RELEASE => {
+            // The result lock verifies the release signatures in witness 0
+            find_output_index(RESULT_TYPE, event_id)?.is_some()
+                && has_vote_secure_input(&own_code, RESULT_TYPE, event_id, None)?
+                && is_release_signer(actor_hash, metadata.signature_scheme)?
+        }
+        AUDIT_OP_WITHDRAWAL => {
+            bytes_equal(actor_hash, &metadata.organizer_lock_hash)
+                && verify_organizer_spend(&metadata, DOMAIN_WITHDRAW).is_ok()
+        }
+        _ => false,
+    };
+    
+    if !matches {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    Ok(())
+}
+
+/// Whether an input is held under the VoteSecure lock with these args
+///
+/// That lock then runs in this transaction, so the operation it guards was
+/// checked by it rather than merely appearing in the outputs.
+fn has_vote_secure_input(
+    own_code: &ScriptCode,
+    cell_type: u8,
+    event_id: &[u8],
+    voter_hash: Option<&[u8]>,
+) -> Result<bool, ContractError> {
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    
+    for i in 0.. {
+        if scan_cell_by_field(&mut lock_hash, i, SOURCE_INPUT, CELL_FIELD_LOCK_HASH)?.is_none() {
+            break;
+        }
+        if has_vote_secure_lock(i, SOURCE_INPUT, own_code, cell_type, event_id, voter_hash)? {
+            return Ok(true);
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Whether a signer's entry is among the release signatures in witness 0
+fn is_release_signer(signer_hash: &[u8], scheme: u8) -> Result<bool, ContractError> {
+    let (lock_start, lock_len, sig_count) = load_multisig_header(0)?;
+    let entry_size = parse_signer_window(lock_len, sig_count, scheme)?;
+    let mut entry_buf = [0u8; SIGNER_ENTRY_SIZE];
+    
+    for i in 0..sig_count as usize {
+        load_witness_range(&mut entry_buf[..entry_size], lock_start + 1 + i * entry_size, 0)?;
+        if bytes_equal(&signer_entry_hash(&entry_buf[..entry_size], scheme), signer_hash) {
+            return Ok(true);
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Validate the committed structure of a ranked ballot
+///
+/// Duplicate or out-of-range ranks are caught through the ranking proof;
+/// either way a malformed ranked ballot is an encoding error.
+fn verify_ranked_ballot(ballot_data: &[u8], len: usize, candidate_count: u8) -> Result<(), ContractError> {
+    let proof_end = BALLOT_RANKING_PROOF_OFFSET + RANKING_PROOF_SIZE;
+    if len < proof_end || ballot_data.len() < proof_end {
+        return Err(ContractError::Encoding);
+    }
+    
+    let rank_count = ballot_data[BALLOT_RANK_COUNT_OFFSET];
+    if rank_count == 0 || rank_count > candidate_count {
+        return Err(ContractError::Encoding);
+    }
+    
+    if !verify_ranking_proof(
+        &ballot_data[BALLOT_PAYLOAD_OFFSET..BALLOT_PAYLOAD_OFFSET + BALLOT_COMMITMENT_SIZE],
+        rank_count,
+        candidate_count,
+        &ballot_data[BALLOT_RANKING_PROOF_OFFSET..proof_end],
+    ) {
+        return Err(ContractError::Encoding);
+    }
+    
+    Ok(())
+}
+
+/// Verify voter ballot submission
+fn verify_voter_ballot(event_id: &[u8], voter_hash: &[u8]) -> Result<(), ContractError> {
+    // Load metadata
+    let metadata = load_metadata(event_id)?;
+    
+    // An emergency halt blocks every operation on the event
+    if metadata.flags & FLAG_EMERGENCY_HALT != 0 {
+        return Err(ContractError::EmergencyHalt);
+    }
+    
+    // So does a signer pause while a dispute is resolved
+    if metadata.paused != 0 {
+        return Err(ContractError::EventPaused);
+    }
+    
+    // Strict events refuse transactions mixing in another event's cells
+    if metadata.flags & FLAG_STRICT_TX != 0 {
+        verify_no_foreign_cells(event_id)?;
+    }
+    
+    // Get the current time on the event's schedule basis
+    let current_time = load_schedule_time(&metadata)?;
+    
+    // REGISTRATION: a transaction creating the voter's registration cell
+    // is a registration, checked against its own window
+    if find_output_registration(event_id, voter_hash)?.is_some() {
+        return verify_voter_registration(event_id, voter_hash, &metadata, current_time);
+    }
+    
+    // 1. SCHEDULE CHECK: Verify within voting window, including any grace
+    // period for late submissions
+    if current_time < metadata.voting_start {
+        return Err(ContractError::InvalidTiming);
+    }
+    let voting_close = submission_close(&metadata)?;
+    if current_time > voting_close {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    // The voter signs over the new ballot's sequence number so a signature
+    // from an earlier ballot can't be replayed to authorize a revote
+    let mut ballot_buf = [0u8; 512];
+    let (witness_index, ballot_len) = match find_output_ballot(event_id, voter_hash, &mut ballot_buf) {
+        Ok(Some(found)) => found,
+        // No replacement ballot: the voter is withdrawing their vote
+        Ok(None) => return verify_ballot_revocation(event_id, voter_hash, &metadata, current_time),
+        Err(e) => return Err(e),
+    };
+    let sequence_number = ballot_sequence_number(&ballot_buf);
+    
+    // BINDING CHECK: the ballot's embedded fields must match its lock args
+    verify_output_binding(VOTER_TYPE, event_id, Some(voter_hash))?;
+    
+    // A revote must also agree with the prior ballots it is counted against
+    if sequence_number > 0 {
+        verify_prior_ballot_binding(event_id, voter_hash)?;
+    }
+    
+    // REGISTRATION CHECK: events with a registration window only take a
+    // first ballot from a voter spending their registration cell, which
+    // may also fix the ballot the voter committed to
+    if metadata.registration_end != 0 && sequence_number == 0 {
+        let registration = match find_registration_input(event_id, voter_hash)? {
+            Some(found) => found,
+            None => return Err(ContractError::VoterIneligible),
+        };
+        verify_registration_input(voter_hash, &metadata, registration)?;
+        verify_registered_ballot(&metadata, registration, &ballot_buf, ballot_len)?;
+    }
+    
+    // LENGTH CHECK: a declared ciphertext length fixes the payload size, so
+    // an under- or over-sized ballot can't shift the fields tally reads
+    if metadata.ciphertext_len != 0 && ballot_len != expected_ballot_len(&metadata) {
+        return Err(ContractError::Encoding);
+    }
+    
+    // RANKING CHECK: ranked ballots must prove their encrypted ranking is
+    // well formed, since nobody can inspect it until the tally
+    if metadata.ballot_format == BALLOT_FORMAT_RANKED {
+        verify_ranked_ballot(&ballot_buf, ballot_len, metadata.candidate_count)?;
+    }
+    
+    // DEPOSIT CHECK: each ballot locks a refundable deposit to deter spam,
+    // reclaimed through cleanup after the audit period
+    if load_capacity(witness_index, SOURCE_OUTPUT)? < metadata.min_ballot_deposit {
+        return Err(ContractError::InsufficientDeposit);
+    }
+    
+    // FRONTEND CHECK: pinned events only accept ballots built by the
+    // approved frontend, referenced as a cell dep
+    if metadata.flags & FLAG_PIN_FRONTEND != 0 && !has_cell_dep_with_data_hash(&metadata.frontend_code_hash)? {
+        return Err(ContractError::FrontendMismatch);
+    }
+    
+    // SEQUENCE CHECK: the first ballot is 0, each revote is exactly max + 1
+    let expected_sequence = match max_prior_sequence(event_id, voter_hash) {
+        Ok(None) => 0,
+        Ok(Some(max)) => match max.checked_add(1) {
+            Some(next) => next,
+            None => return Err(ContractError::InvalidSequence),
+        },
+        Err(e) => return Err(e),
+    };
+    if sequence_number != expected_sequence {
+        return Err(ContractError::InvalidSequence);
+    }
+    
+    // TIMESTAMP CHECK: a ballot can't be dated past the block time, nor
+    // before the ballot it revises, so the history reads in order
+    let ballot_time = ballot_timestamp(&ballot_buf);
+    if ballot_time > load_current_timestamp()? {
+        return Err(ContractError::InvalidTiming);
+    }
+    if let Some(prior_time) = max_prior_timestamp(event_id, voter_hash)? {
+        if ballot_time < prior_time {
+            return Err(ContractError::InvalidTiming);
+        }
+    }
+    
+    // AUDIT CHAIN: the new ballot must link to the cell it replaces
+    if metadata.flags & FLAG_AUDIT_CHAIN != 0 {
+        verify_chain_link(ballot_len, &ballot_buf)?;
+    }
+    
+    // 2. ELIGIBILITY CHECK
+    let allow_delegation = metadata.flags & FLAG_DELEGATION != 0;
+    match metadata.eligibility_mode {
+        ELIGIBILITY_PUBLIC => {
+            // Public mode: anyone can vote, just verify they have valid signature
+            verify_voter_signature(event_id, voter_hash, sequence_number, witness_index, allow_delegation)?;
+        }
+        ELIGIBILITY_INVITE_KEY => {
+            // Invite key mode: verify voter has valid invite signature
+            // Everything that can fail early is done before the witness is
+            // loaded, so every exit after it wipes the buffer
+            let tx_hash = compute_tx_hash()?;
+            let ballot_message = ballot_signing_message(&tx_hash, event_id, sequence_number);
+            let invite_approval = domain_message(DOMAIN_INVITE, &tx_hash);
+            
+            let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
+                Ok(arr) => arr,
+                Err(_) => return Err(ContractError::VoterIneligible),
+            };
+            
+            // Events without a dedicated invite key fall back to the organizer
+            let invite_issuer = if metadata.invite_key_hash == [0u8; PUBKEY_HASH_SIZE] {
+                &metadata.organizer_lock_hash
+            } else {
+                &metadata.invite_key_hash
+            };
+            
+            let mut witness_buf = [0u8; VOTER_WITNESS_BUFFER_SIZE];
+            let witness_len = load_voter_witness(&mut witness_buf, witness_index)?;
+            
+            // Witness lock format: [voter entry][invite issuer entry]
+            if witness_len < INVITE_WITNESS_SIZE {
+                secure_zero(&mut witness_buf);
+                return Err(ContractError::VoterIneligible);
+            }
+            
+            let voter_checked = verify_signature_by_hash(
+                &voter_hash_array,
+                &witness_buf[..SIGNER_ENTRY_SIZE],
+                &ballot_message,
+            );
+            let invite_checked = verify_signature_by_hash(
+                invite_issuer,
+                &witness_buf[SIGNER_ENTRY_SIZE..INVITE_WITNESS_SIZE],
+                &invite_approval,
+            );
+            secure_zero(&mut witness_buf);
+            
+            if let Err(failure) = voter_checked {
+                return Err(signature_error(failure, ContractError::InvalidSignature));
+            }
+            // Verify invite key signature
+            if let Err(failure) = invite_checked {
+                return Err(signature_error(failure, ContractError::VoterIneligible));
+            }
+        }
+        ELIGIBILITY_INVITE_BLS => {
+            // Aggregate invite mode: one BLS aggregate over the voter's
+            // invite proves membership without naming the issuing invite
+            if metadata.bls_aggregate_pubkey == [0u8; BLS_PUBKEY_SIZE] {
+                return Err(ContractError::InvalidArgs);
+            }
+            
+            // Witness lock format: [voter entry][aggregate signature], loaded
+            // once for both checks
+            let mut witness_buf = [0u8; VOTER_WITNESS_BUFFER_SIZE];
+            let witness_len = load_voter_witness(&mut witness_buf, witness_index)?;
+            
+            let voter_checked = check_voter_witness(
+                event_id,
+                voter_hash,
+                sequence_number,
+                &witness_buf,
+                witness_len,
+                allow_delegation,
+            );
+            let valid = witness_len >= BLS_INVITE_WITNESS_SIZE
+                && verify_bls_aggregate(
+                    &metadata.bls_aggregate_pubkey,
+                    &witness_buf[SIGNER_ENTRY_SIZE..BLS_INVITE_WITNESS_SIZE],
+                    &invite_message(event_id, voter_hash),
+                );
+            secure_zero(&mut witness_buf);
+            
+            voter_checked?;
+            if !valid {
+                return Err(ContractError::VoterIneligible);
+            }
+        }
+        ELIGIBILITY_CURATED_LIST => {
+            // Curated list: the voter must have a leaf in the approved list
+            if find_curated_weight_cap(event_id, voter_hash)?.is_none() {
+                return Err(ContractError::VoterIneligible);
+            }
+            
+            verify_voter_signature(event_id, voter_hash, sequence_number, witness_index, allow_delegation)?;
+        }
+        ELIGIBILITY_TOKEN_GATED => {
+            // Token gated: voter must hold the gating token in this transaction
+            if metadata.token_type_hash == [0u8; 32] {
+                return Err(ContractError::InvalidArgs);
+            }
+            if !has_input_with_type_hash(&metadata.token_type_hash)? {
+                return Err(ContractError::VoterIneligible);
+            }
+            
+            verify_voter_signature(event_id, voter_hash, sequence_number, witness_index, allow_delegation)?;
+        }
+        _ => {
+            return Err(ContractError::InvalidArgs);
+        }
+    }
+    
+    // WEIGHT CHECK: a weighted ballot may not exceed the voter's cap from
+    // their curated list leaf
+    if metadata.flags & FLAG_WEIGHTED_VOTING != 0 {
+        if metadata.eligibility_mode != ELIGIBILITY_CURATED_LIST {
+            return Err(ContractError::InvalidArgs);
+        }
+        
+        let weight = ballot_weight(&ballot_buf, ballot_len, true, ballot_payload_size(&metadata))?;
+        let weight_cap = match find_curated_weight_cap(event_id, voter_hash) {
+            Ok(Some(cap)) => cap,
+            Ok(None) => return Err(ContractError::VoterIneligible),
+            Err(e) => return Err(e),
+        };
+        
+        if weight == 0 || weight > weight_cap {
+            return Err(ContractError::VoterIneligible);
+        }
+    }
+    
+    // K-ANONYMITY AT SUBMISSION: when enabled, ballots must stay sealed
+    // until enough voters have participated for none to be singled out
+    if metadata.flags & FLAG_SEAL_UNTIL_K != 0 {
+        if ballot_len <= BALLOT_SEAL_OFFSET {
+            return Err(ContractError::Encoding);
+        }
+        let seal = ballot_buf[BALLOT_SEAL_OFFSET];
+        
+        if seal == BALLOT_DECODABLE {
+            let mut signers = [[0u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS];
+            let signer_count = load_authorized_signers(event_id, &mut signers)?;
+            
+            // Participation so far, including this ballot. Without an
+            // attestation, count distinct voters among the inputs: a voter
+            // whose prior ballots are spent here is counted once, not per
+            // cell, and not again for this ballot
+            let participants = match load_turnout_attestation(event_id, &metadata, &signers, signer_count) {
+                Ok(Some((count, _))) => count + 1,
+                Ok(None) => {
+                    let is_new = count_voter_ballots(event_id, voter_hash, SOURCE_INPUT)? == 0;
+                    count_unique_voters(event_id)? + is_new as u32
+                }
+                Err(e) => return Err(e),
+            };
+            
+            if participants < metadata.k_anonymity_threshold as u32 {
+                return Err(ContractError::KAnonymityViolation);
+            }
+        } else if seal != BALLOT_SEALED {
+            return Err(ContractError::Encoding);
+        }
+    }
+    
+    // 3. REVOTING LIMIT CHECK
+    // max_revotes counts votes after the first: 0 allows a single ballot,
+    // n allows the first ballot plus n revotes. The sequence number, checked
+    // above to follow the voter's prior ballots, is the revote count in
+    // either revote mode; counting input cells would see only one in
+    // replace mode
+    if metadata.max_revotes < 255 && sequence_number > metadata.max_revotes as u32 {
+        return Err(ContractError::RevoteLimitExceeded);
+    }
+    
+    // REPLACE MODE: a revote consumes the prior ballot, so the voter ends
+    // the transaction with as many cells as they started with
+    if metadata.revote_mode == REVOTE_MODE_REPLACE && sequence_number > 0 {
+        let consumed = count_voter_ballots(event_id, voter_hash, SOURCE_INPUT)?;
+        let created = count_voter_ballots(event_id, voter_hash, SOURCE_OUTPUT)?;
+        
+        if consumed == 0 || consumed != created {
+            return Err(ContractError::InvalidSequence);
+        }
+    }
+    
+    // 4. VERIFY EVENTFUND IS PAYING
+    // With fund binding the ballot names the paying input; the fund's own
+    // lock checks no input is claimed by more ballots than it covers
+    if metadata.flags & FLAG_FUND_BINDING != 0 {
+        let fund_index = ballot_fund_ref(&ballot_buf, ballot_len, ballot_fund_ref_offset(&metadata))?;
+        let mut buf = [0u8; CELL_PREFIX_SIZE];
+        let len = match load_cell_prefix(&mut buf, fund_index, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => return Err(ContractError::EventfundMisuse),
+        };
+        if !prefix_matches(&buf, len, EVENTFUND_TYPE, event_id, None) {
+            return Err(ContractError::EventfundMisuse);
+        }
+        return Ok(());
+    }
+    
+    // Otherwise check that an EventFund cell for this event is in inputs
+    let mut found_eventfund = false;
+    let mut buf = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut buf, i, SOURCE_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if len >= 33 && cell_type_of(buf[0]) == EVENTFUND_TYPE && bytes_equal(&buf[1..33], event_id) {
+            found_eventfund = true;
+            break;
+        }
+    }
+    
+    if !found_eventfund {
+        return Err(ContractError::EventfundMisuse);
+    }
+    
+    Ok(())
+}
+
+/// Verify a voter registering before voting opens (VOTER_REGISTER)
+///
+/// The voter spends a cell under their own lock, signs the transaction and
+/// receives exactly one registration cell for the event in return, signed
+/// over its contents. Their first ballot must then spend it.
+fn verify_voter_registration(
+    event_id: &[u8],
+    voter_hash: &[u8],
+    metadata: &ParsedMetadata,
+    current_time: u64,
+) -> Result<(), ContractError> {
+    if metadata.registration_end == 0
+        || current_time < metadata.registration_start
+        || current_time >= metadata.registration_end
+    {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    // Everything created under this lock must be the registration itself
+    verify_output_binding(REGISTRATION_TYPE, event_id, Some(voter_hash))?;
+    
+    // Registering twice would let one voter spend a registration per ballot
+    if find_registration_input(event_id, voter_hash)?.is_some() {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    // The registration is bare or carries a ballot commitment
+    let (registration_index, registration_len) = match find_output_registration(event_id, voter_hash)? {
+        Some(found) => found,
+        None => return Err(ContractError::InvalidArgs),
+    };
+    verify_registration_signature(voter_hash, registration_index, registration_len, SOURCE_OUTPUT)?;
+    
+    let own_lock_hash = load_group_lock_hash()?;
+    let mut registrations = 0u32;
+    let mut lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+    for i in 0.. {
+        if scan_cell_by_field(&mut lock_hash, i, SOURCE_OUTPUT, CELL_FIELD_LOCK_HASH)?.is_none() {
+            break;
+        }
+        if bytes_equal(&lock_hash, &own_lock_hash) {
+            registrations += 1;
+        }
+    }
+    if registrations != 1 {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    let mut witness_buf = [0u8; 256];
+    if let Err(e) = load_witness_lock(&mut witness_buf, 0) {
+        return Err(witness_error(e, ContractError::InvalidSignature));
+    }
+    
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(DOMAIN_REGISTER, &tx_hash);
+    
+    let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
+        Ok(arr) => arr,
+        Err(_) => return Err(ContractError::InvalidArgs),
+    };
+    
+    if let Err(failure) = verify_signature_by_hash(&voter_hash_array, &witness_buf, &message) {
+        return Err(signature_error(failure, ContractError::InvalidSignature));
+    }
+    
+    Ok(())
+}
+
+/// Check a registration input was created in the window and signed
+///
+/// A cell's lock doesn't run when the cell is created, so a registration
+/// cell under the voter's lock proves nothing until it is spent: the block
+/// that created it must fall in the registration window, and the cell
+/// must carry the voter's signature over its contents.
+fn verify_registration_input(
+    voter_hash: &[u8],
+    metadata: &ParsedMetadata,
+    registration: (usize, usize),
+) -> Result<(), ContractError> {
+    let (registration_index, registration_len) = registration;
+    
+    let created = load_cell_schedule_time(metadata, registration_index, SOURCE_GROUP_INPUT)?;
+    if created < metadata.registration_start || created >= metadata.registration_end {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    verify_registration_signature(voter_hash, registration_index, registration_len, SOURCE_GROUP_INPUT)
+}
+
+/// Check the voter's signature closing a registration cell
+///
+/// The signer entry follows the signed contents: event_id, voter hash and
+/// any ballot commitment, signed as blake2b(DOMAIN_REGISTER || contents).
+fn verify_registration_signature(
+    voter_hash: &[u8],
+    index: usize,
+    len: usize,
+    source: u64,
+) -> Result<(), ContractError> {
+    if len != REGISTRATION_BARE_SIZE && len != REGISTRATION_COMMITTED_SIZE {
+        return Err(ContractError::Encoding);
+    }
+    
+    let mut data = [0u8; REGISTRATION_COMMITTED_SIZE];
+    load_cell_data(&mut data[..len], index, source)?;
+    let signed_end = len - SIGNER_ENTRY_SIZE;
+    
+    const TAG_LEN: usize = DOMAIN_REGISTER.len();
+    let mut preimage = [0u8; TAG_LEN + REGISTRATION_COMMITTED_SIZE];
+    preimage[..TAG_LEN].copy_from_slice(DOMAIN_REGISTER);
+    preimage[TAG_LEN..TAG_LEN + signed_end - 1].copy_from_slice(&data[1..signed_end]);
+    let mut message = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&preimage[..TAG_LEN + signed_end - 1], &mut message);
+    
+    let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
+        Ok(arr) => arr,
+        Err(_) => return Err(ContractError::InvalidArgs),
+    };
+    
+    if let Err(failure) = verify_signature_by_hash(&voter_hash_array, &data[signed_end..len], &message) {
+        return Err(signature_error(failure, ContractError::InvalidSignature));
+    }
+    
+    Ok(())
+}
+
+/// Find the output registration cell for a voter
+///
+/// Returns the output index and data length.
+fn find_output_registration(event_id: &[u8], voter_hash: &[u8]) -> Result<Option<(usize, usize)>, ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_OUTPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if prefix_matches(&prefix, len, REGISTRATION_TYPE, event_id, Some(voter_hash)) {
+            return Ok(Some((i, len)));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Find the registration cell for the voter spent by this script group
+///
+/// Returns the group input index and data length. Only the group is
+/// searched: a registration cell under any other lock could have been
+/// created without the voter's signature.
+fn find_registration_input(event_id: &[u8], voter_hash: &[u8]) -> Result<Option<(usize, usize)>, ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    
+    for i in 0.. {
+        let len = match load_cell_prefix(&mut prefix, i, SOURCE_GROUP_INPUT)? {
+            Some(len) => len,
+            None => break,
+        };
+        
+        if prefix_matches(&prefix, len, REGISTRATION_TYPE, event_id, Some(voter_hash)) {
+            return Ok(Some((i, len)));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Check a first ballot against the commitment in the voter's registration
+///
+/// A registration carrying a commitment fixes the encrypted ballot in
+/// advance, so nobody handling the ballot afterwards can substitute it.
+/// Bare registrations commit to nothing.
+fn verify_registered_ballot(
+    metadata: &ParsedMetadata,
+    registration: (usize, usize),
+    ballot_data: &[u8],
+    ballot_len: usize,
+) -> Result<(), ContractError> {
+    let (registration_index, registration_len) = registration;
+    if registration_len != REGISTRATION_COMMITTED_SIZE {
+        return Ok(());
+    }
+    
+    let mut commitment = [0u8; BLAKE2B_HASH_SIZE];
+    load_cell_data_at(
+        &mut commitment,
+        REGISTRATION_COMMITMENT_OFFSET,
+        registration_index,
+        SOURCE_GROUP_INPUT,
+    )?;
+    
+    let payload_end = BALLOT_PAYLOAD_OFFSET + ballot_payload_size(metadata);
+    if ballot_len < payload_end || ballot_data.len() < payload_end {
+        return Err(ContractError::Encoding);
+    }
+    
+    let mut ballot_hash = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(&ballot_data[BALLOT_PAYLOAD_OFFSET..payload_end], &mut ballot_hash);
+    if !bytes_equal_ct(&ballot_hash, &commitment) {
+        return Err(ContractError::BallotCommitmentMismatch);
+    }
+    
+    Ok(())
+}
+
+/// Verify a voter revoking their ballot (VOTER_REVOKE)
+///
+/// The voter consumes their voter cell without creating a successor, which
+/// removes the ballot from every later count and tally. Only the owning
+/// voter can revoke, and only while ballots are still accepted: until
+/// voting_end plus any submission grace period.
+fn verify_ballot_revocation(
+    event_id: &[u8],
+    voter_hash: &[u8],
+    metadata: &ParsedMetadata,
+    current_time: u64,
+) -> Result<(), ContractError> {
+    if current_time > submission_close(metadata)? {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    // A cell under this lock that isn't a ballot for this voter is malformed
+    verify_output_binding(VOTER_TYPE, event_id, Some(voter_hash))?;
+    
+    let mut witness_buf = [0u8; 256];
+    if let Err(e) = load_witness_lock(&mut witness_buf, 0) {
+        return Err(witness_error(e, ContractError::InvalidSignature));
+    }
+    
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(DOMAIN_REVOKE, &tx_hash);
+    
+    let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
+        Ok(arr) => arr,
+        Err(_) => return Err(ContractError::InvalidArgs),
+    };
+    
+    if let Err(failure) = verify_signature_by_hash(&voter_hash_array, &witness_buf, &message) {
+        return Err(signature_error(failure, ContractError::InvalidSignature));
+    }
+    
+    Ok(())
+}
+
+/// Verify result release
+fn verify_result_release(event_id: &[u8]) -> Result<(), ContractError> {
+    // Load metadata
+    let metadata = load_metadata(event_id)?;
+    
+    // An emergency halt blocks every operation on the event
+    if metadata.flags & FLAG_EMERGENCY_HALT != 0 {
+        return Err(ContractError::EmergencyHalt);
+    }
+    
+    // So does a signer pause while a dispute is resolved
+    if metadata.paused != 0 {
+        return Err(ContractError::EventPaused);
+    }
+    
+    // Strict events refuse transactions mixing in another event's cells
+    if metadata.flags & FLAG_STRICT_TX != 0 {
+        verify_no_foreign_cells(event_id)?;
+    }
+    
+    // Get the current time on the event's schedule basis
+    let current_time = load_schedule_time(&metadata)?;
+    
+    // Nothing run after voting may slip a ballot in alongside it
+    verify_no_late_ballots(event_id, &metadata, current_time)?;
+    
+    // Check if this is result release (during audit period)
+    // OR result cleanup (after audit period)
+    let mut is_cleanup = current_time >= metadata.audit_end_time;
+    
+    // Instant finalize events have no audit window, so release and cleanup
+    // overlap; a transaction producing the result cell is a release
+    if is_cleanup && metadata.flags & FLAG_INSTANT_FINALIZE != 0 {
+        let mut result_buf = [0u8; 512];
+        if find_result_output(event_id, &mut result_buf).is_ok() {
+            is_cleanup = false;
+        }
+    }
+    
+    if is_cleanup {
+        // Allow cleanup after audit period, authorized like any other
+        // organizer spend
+        return verify_organizer_spend(&metadata, DOMAIN_CLEANUP);
+    }
+    
+    // 1. TIMELOCK CHECK: Verify voting has ended (results can be decoded)
+    // An earlier release is only allowed with every authorized signer's
+    // consent, which is checked once the signer set is loaded below. A
+    // grace period for late ballots pushes the timelock back with it.
+    let voting_close = submission_close(&metadata)?;
+    let early_release = current_time < voting_close;
+    
+    // AMENDMENT: consuming a released result republishes a corrected one,
+    // which is only allowed while the audit period is open
+    let amending = is_released_result(event_id)?;
+    if amending && (current_time <= voting_close || current_time >= metadata.audit_end_time) {
+        return Err(ContractError::InvalidTiming);
+    }
+    
+    // 2. MULTISIG CHECK: Verify required signatures
+    // Format: [sig_count: u8][[pubkey][sig: 64]]...
+    let (lock_start, lock_len, sig_count) = load_multisig_header(0)?;
+    // Reject a count the witness can't hold before judging it too low
+    parse_signer_window(lock_len, sig_count, metadata.signature_scheme)?;
+    
+    if sig_count < metadata.required_signatures {
+        return Err(ContractError::InsufficientSignatures);
+    }
+    
+    // Compute message hash for signature verification
+    let tx_hash = compute_tx_hash()?;
+    let message = domain_message(DOMAIN_RELEASE, &tx_hash);
+    
+    // Verify each signature using production crypto
+    // Load authorized signers from metadata
+    let mut signers = [[0u8; PUBKEY_HASH_SIZE]; MAX_AUTHORIZED_SIGNERS];
+    let signer_count = load_authorized_signers(event_id, &mut signers)?;
+    
+    // Early release requires unanimity rather than the normal threshold
+    if early_release && (signer_count == 0 || sig_count as usize != signer_count) {
+        return Err(ContractError::TimelockNotExpired);
+    }
+    
+    // So does overriding a published result
+    if amending && (signer_count == 0 || sig_count as usize != signer_count) {
+        return Err(ContractError::InsufficientSignatures);
+    }
+    
+    bench_phase(BENCH_PHASE_SIGNATURES, || {
+        verify_signer_signatures(lock_start, lock_len, sig_count, metadata.signature_scheme, &signers, signer_count, &message)
+    })?;
+    
+    // 3. K-ANONYMITY CHECK: Verify minimum voters participated
+    let mut attested_root = None;
+    let voter_count = bench_phase(BENCH_PHASE_K_ANONYMITY, || {
+        // Prefer a signed turnout attestation over scanning every input
+        let voter_count = match load_turnout_attestation(event_id, &metadata, &signers, signer_count) {
+            Ok(Some((count, root))) => {
+                attested_root = Some(root);
+                count
+            }
+            Ok(None) => count_unique_voters(event_id)?,
+            Err(e) => return Err(e),
+        };
+        
+        if voter_count < metadata.k_anonymity_threshold as u32 {
+            return Err(ContractError::KAnonymityViolation);
+        }
+        
+        // Segmented events also hold each cohort to its own minimum; the
+        // attestation only covers turnout, so this always scans the inputs
+        verify_cohort_k_anonymity(event_id, &metadata)?;
+        
+        Ok(voter_count)
+    })?;
+    
+    // The result must be minted for the event whose metadata validated it
+    verify_output_binding(RESULT_TYPE, event_id, None)?;
+    
+    // Exactly one result may be released, or two contradictory results
+    // could be created for the same event
+    if count_output_cells(RESULT_TYPE, event_id)? > 1 {
+        return Err(ContractError::DuplicateResult);
+    }
+    
+    // 4. TALLY VERIFICATION: Ensure result matches voter cells
+    // Decrypted ballots are revealed in the witness after the signatures
+    // Format: [reveal_count: u16][[option: u8][salt: 32]]...
+    let mut result_buf = [0u8; 512];
+    let result_len = find_result_output(event_id, &mut result_buf)?;
+    // Only act on fully loaded data, never on the zero padding past it
+    if result_len > result_buf.len() {
+        return Err(ContractError::Encoding);
+    }
+    let result_data = &result_buf[..result_len];
+    
+    // The published participation must be the unique voter count above
+    if result_len < RESULT_TALLY_COUNT_OFFSET {
+        return Err(ContractError::InvalidTally);
+    }
+    let total_votes = u32::from_le_bytes([
+        result_data[RESULT_TOTAL_VOTES_OFFSET],
+        result_data[RESULT_TOTAL_VOTES_OFFSET + 1],
+        result_data[RESULT_TOTAL_VOTES_OFFSET + 2],
+        result_data[RESULT_TOTAL_VOTES_OFFSET + 3],
+    ]);
+    if total_votes != voter_count {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    // It must also record how many signatures authorized it
+    if result_data[RESULT_SIGNATURE_COUNT_OFFSET] != sig_count {
+        return Err(ContractError::InvalidTally);
+    }
+    
+    // AUDIT CHAIN: the result must link to the cell it replaces
+    if metadata.flags & FLAG_AUDIT_CHAIN != 0 {
+        verify_chain_link(result_len, &result_buf)?;
+    }
+    
+    let mut reveal_offset = 1 + sig_count as usize * signer_entry_size(metadata.signature_scheme);
+    if reveal_offset > lock_len {
+        return Err(ContractError::Encoding);
+    }
+    
+    // An amendment states why in a reason byte ahead of the reveal, so
+    // auditors can tell a tally correction from a dispute outcome
+    if amending {
+        if reveal_offset >= lock_len {
+            return Err(ContractError::Encoding);
+        }
+        let mut reason = [0u8; 1];
+        load_witness_range(&mut reason, lock_start + reveal_offset, 0)?;
+        if reason[0] == AMENDMENT_REASON_NONE {
+            return Err(ContractError::Encoding);
+        }
+        reveal_offset += 1;
+    }
+    
+    // The reveal is checked as a whole, so load everything after the
+    // signatures at once
+    let mut reveal_buf = [0u8; MAX_REVEAL_SIZE];
+    let reveal_len = lock_len - reveal_offset;
+    if reveal_len > reveal_buf.len() {
+        return Err(ContractError::InvalidReveal);
+    }
+    
+    // The reveal holds decrypted ballots and possibly the event key, so the
+    // buffer is wiped before any result is acted on
+    let tally_result = bench_phase(BENCH_PHASE_TALLY, || {
+        match load_witness_range(&mut reveal_buf[..reveal_len], lock_start + reveal_offset, 0) {
+            Ok(_) => verify_revealed_tally(event_id, &metadata, result_data, &reveal_buf[..reveal_len]),
+            Err(e) => Err(e),
+        }
+    });
+    secure_zero(&mut reveal_buf);
+    
+    let tally_counts = tally_result?;
+    
+    // An attested turnout only stands for the ballots tallied here
+    if let Some(root) = attested_root {
+        if !bytes_equal_ct(&compute_ballot_root(event_id, ballot_payload_size(&metadata))?, &root) {
+            return Err(ContractError::InvalidTally);
+        }
+    }
+    
+    // 5. QUORUM CHECK: Enough ballots participated for the result to stand
+    // Abstentions only count toward quorum if the organizer opted in
+    if metadata.quorum > 0 {
+        let participation = if metadata.flags & FLAG_ABSTAIN_COUNTS_FOR_QUORUM != 0 {
+            tally_counts.ballots
+        } else {
+            tally_counts.ballots - tally_counts.abstentions
+        };
+        
+        if participation < metadata.quorum as u64 {
+            return Err(ContractError::QuorumNotMet);
+        }
+    }
+    
+    // Per-option caps are checked against the released tally. Ballots are
+    // encrypted, so the chosen option is unknown at submission time and the
+    // cap can only be enforced here.
+    verify_option_caps(event_id, &tally_counts)
 }
 
-/// Compute transaction hash for signature verification
-fn compute_tx_hash() -> [u8; 32] {
-    // In production: compute actual transaction hash
-    // This would serialize the transaction and hash it
-    // For now, return a placeholder
-    let mut hash = [0u8; 32];
+/// Whether a released result for the event is referenced by the transaction
+///
+/// The result may be a cell dep, or an input when it is cleaned up in the
+/// same transaction as the fund withdrawal. Either way it exists on chain.
+/// Anyone can create a cell with result-shaped data, so only one under the
+/// event's VoteSecure result lock counts.
+fn has_finalized_result(event_id: &[u8]) -> Result<bool, ContractError> {
+    let own_code = load_own_script_code()?;
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    
+    for source in [SOURCE_CELL_DEP, SOURCE_INPUT] {
+        for i in 0.. {
+            let len = match load_cell_prefix(&mut prefix, i, source)? {
+                Some(len) => len,
+                None => break,
+            };
+            
+            if len >= RESULT_RELEASED_MIN_SIZE
+                && prefix_matches(&prefix, len, RESULT_TYPE, event_id, None)
+                && has_vote_secure_lock(i, source, &own_code, RESULT_TYPE, event_id, None)?
+            {
+                return Ok(true);
+            }
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Whether this transaction consumes an already released result
+fn is_released_result(event_id: &[u8]) -> Result<bool, ContractError> {
+    let mut prefix = [0u8; CELL_PREFIX_SIZE];
+    let len = load_cell_prefix(&mut prefix, 0, SOURCE_GROUP_INPUT)?.unwrap_or(0);
+    
+    Ok(len >= RESULT_RELEASED_MIN_SIZE && prefix_matches(&prefix, len, RESULT_TYPE, event_id, None))
+}
+
+/// Rebuild the reveal key from the trustee shares at the start of a reveal
+///
+/// Shares whose proof fails, whose trustee is unknown, or whose trustee
+/// already contributed are skipped; the first `trustee_threshold` valid
+/// ones rebuild the key. Returns the key and the bytes the shares took up.
+fn combine_reveal_shares(metadata: &ParsedMetadata, reveal: &[u8]) -> Result<([u8; REVEAL_KEY_SIZE], usize), ContractError> {
+    if reveal.is_empty() {
+        return Err(ContractError::InvalidReveal);
+    }
+    let shares_len = 1 + reveal[0] as usize * SHARE_ENTRY_SIZE;
+    if shares_len > reveal.len() {
+        return Err(ContractError::InvalidReveal);
+    }
+    
+    let threshold = metadata.trustee_threshold as usize;
+    let mut xs = [0u8; MAX_TRUSTEES];
+    let mut shares = [[0u8; REVEAL_KEY_SIZE]; MAX_TRUSTEES];
+    let mut valid = 0usize;
     
-    // In real implementation, this would:
-    // 1. Serialize all transaction inputs, outputs, cell deps
-    // 2. Compute blake2b hash of the serialized data
-    // 3. Return the hash
+    for entry in reveal[1..shares_len].chunks_exact(SHARE_ENTRY_SIZE) {
+        if valid == threshold {
+            break;
+        }
+        
+        let trustee = entry[0] as usize;
+        if trustee >= metadata.trustee_count {
+            continue;
+        }
+        // Share points are the 1-based trustee index; 0 holds the secret
+        let x = entry[0] + 1;
+        if xs[..valid].contains(&x) {
+            continue;
+        }
+        
+        let share = &entry[1..1 + REVEAL_KEY_SIZE];
+        let proof = &entry[1 + REVEAL_KEY_SIZE..];
+        if !verify_share_proof(&metadata.trustee_commitments[trustee], share, proof) {
+            continue;
+        }
+        
+        xs[valid] = x;
+        shares[valid].copy_from_slice(share);
+        valid += 1;
+    }
+    
+    if valid < threshold {
+        secure_zero(shares.as_flattened_mut());
+        return Err(ContractError::InsufficientShares);
+    }
+    
+    let key = combine_shares(&xs[..valid], &shares[..valid]);
+    secure_zero(shares.as_flattened_mut());
+    
+    Ok((key, shares_len))
+}
+
+/// Check the revealed ballots in the release witness against the result
+///
+/// Events encrypting ballots to a threshold key carry the revealed key
+/// ahead of the tally reveal, bound to the commitment in metadata.
+fn verify_revealed_tally(
+    event_id: &[u8],
+    metadata: &ParsedMetadata,
+    result_data: &[u8],
+    reveal: &[u8],
+) -> Result<TallyCounts, ContractError> {
+    let mut tally_offset = 0;
+    
+    if metadata.trustee_threshold > 0 {
+        let (mut key, shares_len) = combine_reveal_shares(metadata, reveal)?;
+        
+        let mut key_hash = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&key, &mut key_hash);
+        secure_zero(&mut key);
+        if !bytes_equal_ct(&key_hash, &metadata.reveal_key_hash) {
+            return Err(ContractError::InvalidReveal);
+        }
+        
+        tally_offset = shares_len;
+    } else if metadata.reveal_key_hash != [0u8; BLAKE2B_HASH_SIZE] {
+        if REVEAL_KEY_SIZE > reveal.len() {
+            return Err(ContractError::InvalidReveal);
+        }
+        
+        let mut key_hash = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&reveal[..REVEAL_KEY_SIZE], &mut key_hash);
+        if !bytes_equal_ct(&key_hash, &metadata.reveal_key_hash) {
+            return Err(ContractError::InvalidReveal);
+        }
+        
+        tally_offset = REVEAL_KEY_SIZE;
+    }
     
-    // Placeholder: use first input's lock hash as message
-    let _ = load_input_by_field(&mut hash, 0, CELL_FIELD_LOCK_HASH);
+    verify_tally(event_id, metadata, result_data, &reveal[tally_offset..])
+}
+
+/// Count ballots for an event without validating or authorizing anything
+///
+/// Intended for auditors running the contract off-chain against a
+/// transaction whose inputs hold the event's voter cells. Counts every voter
+/// cell for the event, or only one voter's cells when `voter_hash` is given.
+///
+/// Exit code encoding:
+/// - 1 + count for counts 0..=125 (so a query never exits with SUCCESS and
+///   can't be used to unlock a cell)
+/// - QUERY_MAX_EXIT_CODE (127) for 126 or more ballots
+/// - negative values are the usual error codes
+fn run_query(event_id: &[u8], voter_hash: Option<&[u8]>) -> Result<i8, ContractError> {
+    let count = match voter_hash {
+        Some(hash) => count_voter_ballots(event_id, hash, SOURCE_INPUT)?,
+        None => count_event_voters(event_id, (QUERY_MAX_EXIT_CODE - 1) as u32)?,
+    };
     
-    hash
+    if count >= (QUERY_MAX_EXIT_CODE - 1) as u32 {
+        Ok(QUERY_MAX_EXIT_CODE)
+    } else {
+        Ok(1 + count as i8)
+    }
 }
 
 // ============================================================================
-// Validation Functions
+// Main Entry Point
 // ============================================================================
 
-/// Verify EventFund cell spending
-fn verify_eventfund(event_id: &[u8]) -> i8 {
-    // Load metadata to get organizer info
-    let metadata = match load_metadata(event_id) {
-        Ok(m) => m,
-        Err(e) => return e,
+/// Main entry point
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let result = program_entry();
+    #[cfg(feature = "bench")]
+    bench_report();
+    exit(result);
+}
+
+/// Run the script and convert the outcome to its exit code
+fn program_entry() -> i8 {
+    match dispatch() {
+        Ok(code) => code,
+        Err(e) => e.code(),
+    }
+}
+
+/// Route to the validation for the cell type named in the script args
+fn dispatch() -> Result<i8, ContractError> {
+    // Load script arguments
+    let mut script_buf = [0u8; MAX_SCRIPT_SIZE];
+    
+    let script_len = load_script(&mut script_buf)?;
+    if script_len > script_buf.len() {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    let (_, args_bytes) = parse_script(&script_buf[..script_len])?;
+    let args = parse_script_args(args_bytes)?;
+    let event_id = &args.event_id;
+    let voter_hash = &args.voter_hash;
+    
+    // The cells being spent must be the ones the args describe
+    match args.cell_type {
+        EVENTFUND_TYPE | METADATA_TYPE | RESULT_TYPE => verify_group_binding(args.cell_type, event_id, None)?,
+        VOTER_TYPE => verify_group_binding(VOTER_TYPE, event_id, Some(voter_hash))?,
+        _ => {}
+    }
+    
+    // Route to appropriate validation based on cell type
+    match args.cell_type {
+        EVENTFUND_TYPE => verify_eventfund(event_id)?,
+        METADATA_TYPE => verify_metadata(event_id)?,
+        VOTER_TYPE => verify_voter_ballot(event_id, voter_hash)?,
+        RESULT_TYPE => verify_result_release(event_id)?,
+        // Audit logs are append-only: no operation may spend one
+        AUDITLOG_TYPE => return Err(ContractError::InvalidArgs),
+        QUERY_TYPE => return run_query(event_id, if args.has_voter_hash { Some(voter_hash) } else { None }),
+        _ => return Err(ContractError::InvalidArgs),
+    }
+    
+    // Any audit log written alongside the operation must describe it
+    verify_output_audit_logs(event_id)?;
+    
+    Ok(SUCCESS)
+}
+
+/// Split a molecule `Script` into the code it runs and its args
+///
+/// The table must be exactly the three fields in order, with the args
+/// ending the script, so no bytes are left unaccounted for.
+fn parse_script(script: &[u8]) -> Result<(ScriptCode, &[u8]), ContractError> {
+    if script.len() < SCRIPT_HEADER_SIZE {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([script[offset], script[offset + 1], script[offset + 2], script[offset + 3]]) as usize
+    };
+    if read_u32(0) != script.len()
+        || read_u32(4) != SCRIPT_CODE_HASH_OFFSET
+        || read_u32(8) != SCRIPT_HASH_TYPE_OFFSET
+        || read_u32(12) != SCRIPT_ARGS_OFFSET
+        || SCRIPT_HEADER_SIZE + read_u32(SCRIPT_ARGS_OFFSET) != script.len()
+    {
+        return Err(ContractError::InvalidArgs);
+    }
+    
+    let mut code = ScriptCode {
+        code_hash: [0u8; BLAKE2B_HASH_SIZE],
+        hash_type: script[SCRIPT_HASH_TYPE_OFFSET],
     };
+    code.code_hash.copy_from_slice(&script[SCRIPT_CODE_HASH_OFFSET..SCRIPT_HASH_TYPE_OFFSET]);
     
-    // Get current timestamp
-    let current_time = match load_current_timestamp() {
-        Ok(t) => t,
-        Err(e) => return e,
+    Ok((code, &script[SCRIPT_HEADER_SIZE..]))
+}
+
+/// Decode script args
+///
+/// Legacy:    [type: 1][event_id: 32][voter_hash: 20?]
+/// Version 0: [version: 1][type: 1][event_id: 32][voter_hash: 20?]
+/// Version 1: [version: 1][type: 1][event_id: 32][voter_hash: 20]
+///            [[tag: 1][len: 1][value]]...
+///
+/// Cells created before args were versioned carry the legacy layout. It is
+/// recognised by its exact length (33 or 53 bytes) before any version byte
+/// is read; no versioned layout has those lengths.
+///
+/// Version 1 always carries the voter hash (all zero for non-voter cells),
+/// so extension records can follow it. No extension tags are defined yet:
+/// records tagged ARGS_EXT_OPTIONAL are skipped, so fields can be added
+/// without breaking this script, and any other tag is rejected.
+fn parse_script_args(args: &[u8]) -> Result<ScriptArgs, ContractError> {
+    let mut parsed = ScriptArgs {
+        cell_type: 0,
+        event_id: [0u8; EVENT_ID_SIZE],
+        voter_hash: [0u8; PUBKEY_HASH_SIZE],
+        has_voter_hash: false,
     };
     
-    // Check if this is ballot payment (during voting) or final withdrawal
-    let is_voting_period = current_time >= metadata.voting_start 
-        && current_time <= metadata.voting_end;
-    let is_after_audit = current_time >= metadata.audit_end_time;
+    if args.len() == LEGACY_ARGS_SIZE || args.len() == LEGACY_VOTER_ARGS_SIZE {
+        parsed.cell_type = args[0];
+        parsed.event_id.copy_from_slice(&args[1..33]);
+        if args.len() == LEGACY_VOTER_ARGS_SIZE {
+            parsed.voter_hash.copy_from_slice(&args[33..53]);
+            parsed.has_voter_hash = true;
+        }
+        return Ok(parsed);
+    }
+    
+    // Minimum versioned args: 1 byte version + 1 byte type + 32 bytes event_id
+    if args.len() < 2 + EVENT_ID_SIZE {
+        return Err(ContractError::InvalidArgs);
+    }
+    parsed.cell_type = args[1];
+    parsed.event_id.copy_from_slice(&args[2..34]);
+    
+    match args[0] {
+        ARGS_VERSION_0 => {
+            match args.len() {
+                34 => {}
+                54 => {
+                    parsed.voter_hash.copy_from_slice(&args[34..54]);
+                    parsed.has_voter_hash = true;
+                }
+                _ => return Err(ContractError::InvalidArgs),
+            }
+        }
+        ARGS_VERSION_1 => {
+            if args.len() < 54 {
+                return Err(ContractError::InvalidArgs);
+            }
+            parsed.voter_hash.copy_from_slice(&args[34..54]);
+            parsed.has_voter_hash = parsed.voter_hash != [0u8; PUBKEY_HASH_SIZE];
+            
+            let mut offset = 54;
+            while offset < args.len() {
+                if offset + 2 > args.len() {
+                    return Err(ContractError::InvalidArgs);
+                }
+                let tag = args[offset];
+                let value_end = offset + 2 + args[offset + 1] as usize;
+                if value_end > args.len() {
+                    return Err(ContractError::InvalidArgs);
+                }
+                
+                // No tags are understood yet, so only optional ones pass
+                if tag & ARGS_EXT_OPTIONAL == 0 {
+                    return Err(ContractError::InvalidArgs);
+                }
+                
+                offset = value_end;
+            }
+        }
+        _ => return Err(ContractError::InvalidArgs),
+    }
+    
+    Ok(parsed)
+}
+
+// ============================================================================
+// Panic Handler
+// ============================================================================
+
+#[cfg(not(any(test, feature = "std")))]
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    exit(ContractError::Encoding.code());
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metadata_encoder::EventMetadataBuilder;
+    use mock_chain::{MockCell, MockHeader, MockTx, TestKey};
+    
+    fn args_blob(prefix: &[u8], event_id: u8, voter: Option<u8>) -> std::vec::Vec<u8> {
+        let mut args = prefix.to_vec();
+        args.extend_from_slice(&[event_id; EVENT_ID_SIZE]);
+        if let Some(voter) = voter {
+            args.extend_from_slice(&[voter; PUBKEY_HASH_SIZE]);
+        }
+        args
+    }
+    
+    #[test]
+    fn legacy_args_are_read_by_length() {
+        // A legacy type byte of 0 or 1 must not be taken for a version
+        let args = parse_script_args(&args_blob(&[EVENTFUND_TYPE], 0xAA, None)).ok().unwrap();
+        assert!(args.cell_type == EVENTFUND_TYPE && args.event_id == [0xAA; EVENT_ID_SIZE]);
+        assert!(!args.has_voter_hash);
+        
+        let args = parse_script_args(&args_blob(&[VOTER_TYPE], 0xAA, Some(0xBB))).ok().unwrap();
+        assert!(args.cell_type == VOTER_TYPE && args.has_voter_hash);
+        assert!(args.voter_hash == [0xBB; PUBKEY_HASH_SIZE]);
+    }
+    
+    #[test]
+    fn v0_args_keep_the_legacy_fields() {
+        let args = parse_script_args(&args_blob(&[ARGS_VERSION_0, METADATA_TYPE], 0xAA, None)).ok().unwrap();
+        assert!(args.cell_type == METADATA_TYPE && !args.has_voter_hash);
+        
+        let args = parse_script_args(&args_blob(&[ARGS_VERSION_0, VOTER_TYPE], 0xAA, Some(0xBB))).ok().unwrap();
+        assert!(args.cell_type == VOTER_TYPE && args.voter_hash == [0xBB; PUBKEY_HASH_SIZE]);
+        
+        let mut trailing = args_blob(&[ARGS_VERSION_0, VOTER_TYPE], 0xAA, Some(0xBB));
+        trailing.push(0);
+        assert!(matches!(parse_script_args(&trailing), Err(ContractError::InvalidArgs)));
+    }
+    
+    #[test]
+    fn v1_args_skip_optional_extension_records() {
+        let mut args = args_blob(&[ARGS_VERSION_1, VOTER_TYPE], 0xAA, Some(0xBB));
+        args.extend_from_slice(&[ARGS_EXT_OPTIONAL | 0x01, 3, 1, 2, 3]);
+        let parsed = parse_script_args(&args).ok().unwrap();
+        assert!(parsed.cell_type == VOTER_TYPE && parsed.voter_hash == [0xBB; PUBKEY_HASH_SIZE]);
+        
+        // A zeroed voter hash means the cell has no voter
+        let parsed = parse_script_args(&args_blob(&[ARGS_VERSION_1, RESULT_TYPE], 0xAA, Some(0))).ok().unwrap();
+        assert!(!parsed.has_voter_hash);
+    }
+    
+    #[test]
+    fn v1_args_reject_unknown_or_truncated_records() {
+        let base = args_blob(&[ARGS_VERSION_1, VOTER_TYPE], 0xAA, Some(0xBB));
+        
+        let mut required = base.clone();
+        required.extend_from_slice(&[0x01, 1, 0]);
+        assert!(matches!(parse_script_args(&required), Err(ContractError::InvalidArgs)));
+        
+        let mut truncated = base.clone();
+        truncated.extend_from_slice(&[ARGS_EXT_OPTIONAL, 4, 0]);
+        assert!(matches!(parse_script_args(&truncated), Err(ContractError::InvalidArgs)));
+        
+        let mut dangling = base;
+        dangling.push(ARGS_EXT_OPTIONAL);
+        assert!(matches!(parse_script_args(&dangling), Err(ContractError::InvalidArgs)));
+    }
+    
+    #[test]
+    fn unknown_args_versions_are_rejected() {
+        let args = args_blob(&[2, VOTER_TYPE], 0xAA, Some(0xBB));
+        assert!(matches!(parse_script_args(&args), Err(ContractError::InvalidArgs)));
+        assert!(matches!(parse_script_args(&[ARGS_VERSION_1; 20]), Err(ContractError::InvalidArgs)));
+    }
+    
+    #[test]
+    fn error_codes_are_pinned() {
+        // Exit codes are ABI: off-chain tooling maps them back to reasons
+        let codes = [
+            (ContractError::InvalidArgs, -1),
+            (ContractError::Encoding, -2),
+            (ContractError::Syscall, -3),
+            (ContractError::MetadataNotFound, -4),
+            (ContractError::InvalidTiming, -5),
+            (ContractError::VoterIneligible, -6),
+            (ContractError::RevoteLimitExceeded, -7),
+            (ContractError::TimelockNotExpired, -8),
+            (ContractError::InsufficientSignatures, -9),
+            (ContractError::UnauthorizedWithdrawal, -10),
+            (ContractError::EventfundMisuse, -11),
+            (ContractError::MetadataImmutable, -12),
+            (ContractError::KAnonymityViolation, -13),
+            (ContractError::InvalidTally, -14),
+            (ContractError::InvalidSignature, -15),
+            (ContractError::HeaderMissing, -16),
+            (ContractError::QuorumNotMet, -17),
+            (ContractError::BrokenChain, -18),
+            (ContractError::InvalidSequence, -19),
+            (ContractError::EmergencyHalt, -20),
+            (ContractError::EventPaused, -21),
+            (ContractError::InvalidReveal, -22),
+            (ContractError::DuplicateResult, -23),
+            (ContractError::AmbiguousMetadata, -24),
+            (ContractError::FrontendMismatch, -25),
+            (ContractError::InsufficientDeposit, -26),
+            (ContractError::FundLockedAudit, -27),
+            (ContractError::ResultsNotFinalized, -28),
+            (ContractError::EmptyWitness, -29),
+            (ContractError::InsufficientShares, -30),
+            (ContractError::SignerMismatch, -31),
+            (ContractError::BallotCommitmentMismatch, -32),
+            (ContractError::AmbiguousAttestation, -33),
+            (ContractError::InvalidMetadata, -34),
+        ];
+        for (error, code) in codes {
+            assert_eq!(error.code(), code);
+        }
+    }
+    
+    #[test]
+    fn constant_time_compare_matches_plain_equality() {
+        assert!(bytes_equal_ct(&[1, 2, 3], &[1, 2, 3]));
+        assert!(bytes_equal_ct(&[], &[]));
+        assert!(!bytes_equal_ct(&[1, 2, 3], &[1, 2, 4]));
+        assert!(!bytes_equal_ct(&[0x80, 2, 3], &[0, 2, 3]));
+        assert!(!bytes_equal_ct(&[1, 2, 3], &[1, 2]));
+    }
+    
+    #[test]
+    fn secure_zero_wipes_the_whole_buffer() {
+        let mut buf = [0xA5u8; 97];
+        secure_zero(&mut buf);
+        assert!(buf.iter().all(|&b| b == 0));
+        
+        secure_zero(&mut []);
+    }
+    
+    fn valid_metadata() -> std::vec::Vec<u8> {
+        metadata_encoder::EventMetadataBuilder::new([0xAA; EVENT_ID_SIZE], [0xBB; PUBKEY_HASH_SIZE])
+            .schedule(1_000, 2_000, 3_000)
+            .signer([0x01; PUBKEY_HASH_SIZE])
+            .required_signatures(1)
+            .option_caps(&[5, 5])
+            .flags(FLAG_PRIVACY_MODE)
+            .build()
+            .ok()
+            .unwrap()
+    }
+    
+    proptest::proptest! {
+        // parse_metadata is the one parser fed arbitrary on-chain bytes
+        // without a syscall in between: it must reject, never panic
+        #[test]
+        fn parse_metadata_never_panics_on_arbitrary_bytes(data in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..512)) {
+            let _ = parse_metadata(&data);
+        }
+        
+        #[test]
+        fn parse_metadata_never_panics_on_corrupted_metadata(
+            edits in proptest::collection::vec((0usize..256, proptest::prelude::any::<u8>()), 1..8),
+            truncate in 0usize..256,
+            extension in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..64),
+        ) {
+            let mut data = valid_metadata();
+            data.extend_from_slice(&extension);
+            for (offset, value) in edits {
+                let len = data.len();
+                data[offset % len] = value;
+            }
+            data.truncate(data.len().saturating_sub(truncate % 8));
+            let _ = parse_metadata(&data);
+        }
+    }
+    
+    // Fixtures for running the script against a mock transaction. Every
+    // lock shares CODE_HASH, so cells built here count as VoteSecure cells
+    
+    const CODE_HASH: [u8; BLAKE2B_HASH_SIZE] = [0xC0; BLAKE2B_HASH_SIZE];
+    const EVENT: [u8; EVENT_ID_SIZE] = [0xE1; EVENT_ID_SIZE];
+    const VOTING_START: u64 = 1_000_000;
+    const VOTING_END: u64 = 2_000_000;
+    const AUDIT_END: u64 = 3_000_000;
+    // Ballots spent by the fixtures below were cast in this block
+    const BALLOT_BLOCK: u64 = 1;
+    
+    fn lock(cell_type: u8, event_id: &[u8; EVENT_ID_SIZE], voter_hash: Option<&[u8; PUBKEY_HASH_SIZE]>) -> std::vec::Vec<u8> {
+        let mut args = std::vec![ARGS_VERSION_0, cell_type];
+        args.extend_from_slice(event_id);
+        if let Some(voter_hash) = voter_hash {
+            args.extend_from_slice(voter_hash);
+        }
+        mock_chain::script(CODE_HASH, &args)
+    }
+    
+    fn header(number: u64, timestamp: u64) -> MockHeader {
+        MockHeader { number, epoch: number, timestamp }
+    }
+    
+    /// Voter cell data with the default payload, opening to `commitment`
+    fn ballot_data(
+        event_id: &[u8; EVENT_ID_SIZE],
+        voter_hash: &[u8; PUBKEY_HASH_SIZE],
+        sequence: u32,
+        timestamp: u64,
+        commitment: &[u8; BLAKE2B_HASH_SIZE],
+    ) -> std::vec::Vec<u8> {
+        let mut data = std::vec![VOTER_TYPE];
+        data.extend_from_slice(event_id);
+        data.extend_from_slice(voter_hash);
+        data.extend_from_slice(&sequence.to_le_bytes());
+        data.extend_from_slice(&timestamp.to_le_bytes());
+        data.extend_from_slice(commitment);
+        data.resize(BALLOT_PAYLOAD_OFFSET + BALLOT_PAYLOAD_SIZE, 0);
+        data
+    }
+    
+    #[test]
+    fn mock_loads_follow_the_vm_length_rules() {
+        let voter = [0x11; PUBKEY_HASH_SIZE];
+        let mut tx = MockTx::new(lock(VOTER_TYPE, &EVENT, Some(&voter)));
+        tx.outputs.push(MockCell::new(lock(VOTER_TYPE, &EVENT, Some(&voter)), ballot_data(&EVENT, &voter, 0, 0, &[0; 32])));
+        tx.outputs.push(MockCell::new(mock_chain::script([0x22; 32], &[]), std::vec![VOTER_TYPE; 40]));
+        
+        tx.run(|| {
+            // The full length comes back even when only the prefix is copied
+            let mut prefix = [0u8; CELL_PREFIX_SIZE];
+            let len = load_cell_prefix(&mut prefix, 0, SOURCE_OUTPUT).ok().unwrap();
+            assert_eq!(len, Some(BALLOT_PAYLOAD_OFFSET + BALLOT_PAYLOAD_SIZE));
+            assert!(prefix[33..53] == voter);
+            
+            // A short cell can't leave the previous voter hash behind
+            assert_eq!(load_cell_prefix(&mut prefix, 1, SOURCE_OUTPUT).ok().unwrap(), Some(40));
+            assert!(prefix[40..].iter().all(|&b| b == 0));
+            
+            // Scans end at the first index past the last cell
+            assert!(matches!(load_cell_prefix(&mut prefix, 2, SOURCE_OUTPUT), Ok(None)));
+            
+            let mut sequence = [0u8; 4];
+            let len = load_cell_data_at(&mut sequence, BALLOT_SEQUENCE_OFFSET, 0, SOURCE_OUTPUT).ok().unwrap();
+            assert_eq!(len, BALLOT_PAYLOAD_OFFSET + BALLOT_PAYLOAD_SIZE - BALLOT_SEQUENCE_OFFSET);
+        });
+    }
+    
+    #[test]
+    fn mock_witnesses_are_read_through_witness_args() {
+        let mut tx = MockTx::new(lock(EVENTFUND_TYPE, &EVENT, None));
+        tx.witnesses.push(mock_chain::witness_args(&[0xAB; 97]));
+        tx.witnesses.push(mock_chain::witness_args(&[]));
+        
+        tx.run(|| {
+            let mut buf = [0u8; 256];
+            assert_eq!(load_witness_lock(&mut buf, 0).ok(), Some(97));
+            assert!(buf[..97].iter().all(|&b| b == 0xAB));
+            
+            assert!(matches!(load_witness_lock(&mut buf, 1), Err(ContractError::EmptyWitness)));
+            assert!(matches!(load_witness_lock(&mut buf, 2), Err(ContractError::Syscall)));
+            assert!(matches!(locate_witness_lock(0), Ok((20, 97))));
+        });
+    }
+    
+    // Voting fixtures: a revote spends the voter's previous ballot, cast in
+    // BALLOT_BLOCK, with an EventFund input paying for the new one
+    
+    fn voting_metadata() -> EventMetadataBuilder {
+        EventMetadataBuilder::new(EVENT, [0x0A; PUBKEY_HASH_SIZE])
+            .schedule(VOTING_START, VOTING_END, AUDIT_END)
+            .max_revotes(5)
+    }
+    
+    /// An unsigned revote by `voter` replacing ballot `prior` with `sequence`
+    fn revote_tx(metadata: &[u8], voter: &TestKey, prior: u32, sequence: u32) -> MockTx {
+        let voter_hash = voter.hash();
+        let voter_lock = lock(VOTER_TYPE, &EVENT, Some(&voter_hash));
+        let prior = ballot_data(&EVENT, &voter_hash, prior, VOTING_START + 1, &[0x01; BLAKE2B_HASH_SIZE]);
+        let mut fund = std::vec![EVENTFUND_TYPE];
+        fund.extend_from_slice(&EVENT);
+        
+        let mut tx = MockTx::new(voter_lock.clone());
+        tx.inputs.push(MockCell::new(voter_lock.clone(), prior).created_in(BALLOT_BLOCK));
+        tx.inputs.push(MockCell::new(lock(EVENTFUND_TYPE, &EVENT, None), fund).created_in(BALLOT_BLOCK));
+        tx.cell_deps.push(MockCell::new(mock_chain::script([0x4D; 32], &[]), metadata.to_vec()));
+        tx.header_deps.push(header(BALLOT_BLOCK, VOTING_START + 1));
+        tx.outputs.push(MockCell::new(
+            voter_lock,
+            ballot_data(&EVENT, &voter_hash, sequence, VOTING_START + 1, &[0x02; BLAKE2B_HASH_SIZE]),
+        ));
+        tx
+    }
+    
+    /// Sign the ballot at output 0 as `voter`, over `event_id` and `sequence`
+    fn sign_ballot(tx: &mut MockTx, voter: &TestKey, event_id: &[u8; EVENT_ID_SIZE], sequence: u32) {
+        let message = ballot_signing_message(&tx.hash(), event_id, sequence);
+        tx.witnesses = std::vec![mock_chain::witness_args(&voter.entry(&message))];
+    }
+    
+    #[test]
+    fn ballot_signatures_bind_the_sequence_number_and_event() {
+        let voter = TestKey::new(7);
+        let metadata = voting_metadata().build().ok().unwrap();
+        let mut tx = revote_tx(&metadata, &voter, 1, 2);
+        
+        sign_ballot(&mut tx, &voter, &EVENT, 2);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // A signature over the previous sequence number can't authorize this one
+        sign_ballot(&mut tx, &voter, &EVENT, 1);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSignature.code());
+        
+        // Nor can one made for the same ballot in another event
+        sign_ballot(&mut tx, &voter, &[0xE2; EVENT_ID_SIZE], 2);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSignature.code());
+    }
+    
+    /// An unsigned first ballot spending `voter`'s registration cell
+    ///
+    /// The registration was made before voting opened; the input's since
+    /// dates the spend inside the voting window.
+    fn registered_ballot_tx(metadata: &[u8], voter: &TestKey, sequence: u32) -> MockTx {
+        let voter_hash = voter.hash();
+        let voter_lock = lock(VOTER_TYPE, &EVENT, Some(&voter_hash));
+        
+        let mut registration = std::vec![REGISTRATION_TYPE];
+        registration.extend_from_slice(&EVENT);
+        registration.extend_from_slice(&voter_hash);
+        let mut preimage = DOMAIN_REGISTER.to_vec();
+        preimage.extend_from_slice(&registration[1..]);
+        let mut message = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&preimage, &mut message);
+        registration.extend_from_slice(&voter.entry(&message));
+        
+        let mut registration_cell = MockCell::new(voter_lock.clone(), registration).created_in(0);
+        registration_cell.since = SINCE_METRIC_TIMESTAMP | (VOTING_START / 1000 + 1);
+        let mut fund = std::vec![EVENTFUND_TYPE];
+        fund.extend_from_slice(&EVENT);
+        
+        let mut tx = MockTx::new(voter_lock.clone());
+        tx.inputs.push(registration_cell);
+        tx.inputs.push(MockCell::new(lock(EVENTFUND_TYPE, &EVENT, None), fund).created_in(BALLOT_BLOCK));
+        tx.cell_deps.push(MockCell::new(mock_chain::script([0x4D; 32], &[]), metadata.to_vec()));
+        tx.header_deps.push(header(0, VOTING_START - 500));
+        tx.outputs.push(MockCell::new(
+            voter_lock,
+            ballot_data(&EVENT, &voter_hash, sequence, VOTING_START + 1, &[0x02; BLAKE2B_HASH_SIZE]),
+        ));
+        tx
+    }
     
-    if is_voting_period {
-        // During voting: EventFund can be spent to pay for ballot submission
-        // Verify that an output Voter cell exists with matching event_id
-        let mut buf = [0u8; 512];
-        let mut found_ballot = false;
+    #[test]
+    fn ballot_sequence_numbers_follow_the_voters_prior_ballots() {
+        let voter = TestKey::new(7);
         
-        for i in 0..16 {
-            if load_output_by_field(&mut buf, i, CELL_FIELD_DATA).is_err() {
-                break;
-            }
-            
-            if buf[0] == VOTER_TYPE && bytes_equal(&buf[1..33], event_id) {
-                found_ballot = true;
-                break;
-            }
+        // A first ballot must be sequence 0
+        let mut window = (VOTING_START - 1_000).to_le_bytes().to_vec();
+        window.extend_from_slice(&VOTING_START.to_le_bytes());
+        let registered = voting_metadata()
+            .extension(METADATA_EXT_REGISTRATION_WINDOW, &window)
+            .build()
+            .ok()
+            .unwrap();
+        for (sequence, expected) in [(0, SUCCESS), (1, ContractError::InvalidSequence.code())] {
+            let mut tx = registered_ballot_tx(&registered, &voter, sequence);
+            sign_ballot(&mut tx, &voter, &EVENT, sequence);
+            assert_eq!(tx.run(program_entry), expected);
         }
         
-        if !found_ballot {
-            return ERROR_EVENTFUND_MISUSE;
+        // A revote must be exactly one past the prior ballot: not a repeat,
+        // a stale number or a skip
+        let metadata = voting_metadata().build().ok().unwrap();
+        for (prior, sequence, expected) in [
+            (1, 2, SUCCESS),
+            (2, 2, ContractError::InvalidSequence.code()),
+            (2, 1, ContractError::InvalidSequence.code()),
+            (1, 3, ContractError::InvalidSequence.code()),
+            // No successor to the last sequence number wraps back to 0
+            (u32::MAX, 0, ContractError::InvalidSequence.code()),
+        ] {
+            let mut tx = revote_tx(&metadata, &voter, prior, sequence);
+            sign_ballot(&mut tx, &voter, &EVENT, sequence);
+            assert_eq!(tx.run(program_entry), expected);
         }
-        
-        return SUCCESS;
     }
     
-    if is_after_audit {
-        // After audit period: organizer can withdraw remaining funds
-        // Verify organizer signature
-        let mut witness_buf = [0u8; 256];
-        if load_witness(&mut witness_buf, 0).is_err() {
-            return ERROR_UNAUTHORIZED_WITHDRAWAL;
-        }
+    #[test]
+    fn tx_hash_is_the_hash_the_vm_reports() {
+        let metadata = voting_metadata().build().ok().unwrap();
+        let mut tx = revote_tx(&metadata, &TestKey::new(7), 0, 1);
+        let expected = tx.hash();
         
-        let tx_hash = compute_tx_hash();
+        let mut first_lock_hash = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&tx.inputs[0].lock, &mut first_lock_hash);
         
-        if !verify_signature_by_hash(
-            &metadata.organizer_lock_hash,
-            &witness_buf,
-            &tx_hash,
-        ) {
-            return ERROR_UNAUTHORIZED_WITHDRAWAL;
-        }
+        assert!(matches!(tx.run(compute_tx_hash), Ok(hash) if hash == expected));
+        assert!(expected != first_lock_hash);
         
-        return SUCCESS;
+        // Witnesses aren't covered, so signatures can be added after signing
+        tx.witnesses.push(mock_chain::witness_args(&[0x5A; 97]));
+        assert!(matches!(tx.run(compute_tx_hash), Ok(hash) if hash == expected));
+        
+        // Any change to the transaction body is
+        tx.outputs[0].capacity += 1;
+        assert!(matches!(tx.run(compute_tx_hash), Ok(hash) if hash != expected));
     }
     
-    // Outside valid periods: cannot spend EventFund
-    ERROR_INVALID_TIMING
-}
-
-/// Verify metadata cell operations
-fn verify_metadata(event_id: &[u8]) -> i8 {
-    // Load metadata
-    let metadata = match load_metadata(event_id) {
-        Ok(m) => m,
-        Err(e) => return e,
-    };
+    // Fund fixtures: the organizer's EventFund, created in FUND_BLOCK while
+    // voting is open, pays BALLOT_FEE for each ballot
     
-    // Get current timestamp
-    let current_time = match load_current_timestamp() {
-        Ok(t) => t,
-        Err(e) => return e,
-    };
+    const FUND_BLOCK: u64 = 2;
+    const BALLOT_FEE: u64 = 100_000_000;
+    const FUND_CAPACITY: u64 = 10_000 * 100_000_000;
     
-    // After audit period ends, allow metadata cleanup
-    if current_time >= metadata.audit_end_time {
-        return verify_metadata_cleanup(&metadata);
+    fn fund_metadata(organizer: &TestKey) -> EventMetadataBuilder {
+        EventMetadataBuilder::new(EVENT, organizer.hash())
+            .schedule(VOTING_START, VOTING_END, AUDIT_END)
+            .extension(METADATA_EXT_BALLOT_FEE, &BALLOT_FEE.to_le_bytes())
     }
     
-    // Before audit period ends: metadata is immutable
-    ERROR_METADATA_IMMUTABLE
-}
-
-/// Verify metadata cleanup (consumption after audit period)
-fn verify_metadata_cleanup(metadata: &ParsedMetadata) -> i8 {
-    // Check that organizer is performing the cleanup
-    let mut witness_buf = [0u8; 256];
-    if load_witness(&mut witness_buf, 0).is_err() {
-        return ERROR_UNAUTHORIZED_WITHDRAWAL;
+    fn fund_cell(organizer: &TestKey, capacity: u64) -> MockCell {
+        let mut data = std::vec![EVENTFUND_TYPE];
+        data.extend_from_slice(&EVENT);
+        data.extend_from_slice(&organizer.hash());
+        MockCell::new(lock(EVENTFUND_TYPE, &EVENT, None), data).with_capacity(capacity)
     }
     
-    let tx_hash = compute_tx_hash();
-    
-    // Verify organizer signature using production crypto
-    if !verify_signature_by_hash(
-        &metadata.organizer_lock_hash,
-        &witness_buf,
-        &tx_hash,
-    ) {
-        return ERROR_UNAUTHORIZED_WITHDRAWAL;
+    /// An unsigned batch of first ballots by `voters`, paid from the fund
+    ///
+    /// Ballot i is output i and the fund's change follows the ballots.
+    fn ballot_batch_tx(metadata: &[u8], organizer: &TestKey, voters: &[&TestKey]) -> MockTx {
+        let mut tx = MockTx::new(lock(EVENTFUND_TYPE, &EVENT, None));
+        tx.inputs.push(fund_cell(organizer, FUND_CAPACITY).created_in(FUND_BLOCK));
+        tx.cell_deps.push(MockCell::new(mock_chain::script([0x4D; 32], &[]), metadata.to_vec()));
+        tx.header_deps.push(header(FUND_BLOCK, VOTING_START + 2));
+        
+        let mut spent = 0;
+        for voter in voters {
+            let voter_hash = voter.hash();
+            let data = ballot_data(&EVENT, &voter_hash, 0, VOTING_START + 1, &[0x02; BLAKE2B_HASH_SIZE]);
+            let ballot = MockCell::new(lock(VOTER_TYPE, &EVENT, Some(&voter_hash)), data);
+            spent += ballot.capacity + BALLOT_FEE;
+            tx.outputs.push(ballot);
+        }
+        tx.outputs.push(fund_cell(organizer, FUND_CAPACITY - spent));
+        tx
     }
     
-    SUCCESS
-}
-
-/// Verify voter ballot submission
-fn verify_voter_ballot(event_id: &[u8], voter_hash: &[u8]) -> i8 {
-    // Load metadata
-    let metadata = match load_metadata(event_id) {
-        Ok(m) => m,
-        Err(e) => return e,
-    };
+    /// Sign ballot i of a batch as `voters[i]`
+    fn sign_batch(tx: &mut MockTx, voters: &[&TestKey]) {
+        let message = ballot_signing_message(&tx.hash(), &EVENT, 0);
+        tx.witnesses = voters.iter().map(|voter| mock_chain::witness_args(&voter.entry(&message))).collect();
+    }
     
-    // Get current timestamp
-    let current_time = match load_current_timestamp() {
-        Ok(t) => t,
-        Err(e) => return e,
-    };
+    /// An unsigned organizer withdrawal of the whole fund at `time`
+    fn withdrawal_tx(metadata: &[u8], organizer: &TestKey, time: u64) -> MockTx {
+        let mut tx = MockTx::new(lock(EVENTFUND_TYPE, &EVENT, None));
+        tx.inputs.push(fund_cell(organizer, FUND_CAPACITY).created_in(FUND_BLOCK));
+        tx.cell_deps.push(MockCell::new(mock_chain::script([0x4D; 32], &[]), metadata.to_vec()));
+        tx.header_deps.push(header(FUND_BLOCK, time));
+        tx.outputs.push(MockCell::new(mock_chain::script([0x0B; 32], &organizer.hash()), std::vec::Vec::new()));
+        tx
+    }
     
-    // 1. SCHEDULE CHECK: Verify within voting window
-    if current_time < metadata.voting_start {
-        return ERROR_INVALID_TIMING;
+    fn sign_spend(tx: &mut MockTx, key: &TestKey, domain_tag: &[u8]) {
+        let message = domain_message(domain_tag, &tx.hash());
+        tx.witnesses = std::vec![mock_chain::witness_args(&key.entry(&message))];
     }
-    if current_time > metadata.voting_end {
-        return ERROR_INVALID_TIMING;
+    
+    #[test]
+    fn each_ballot_in_a_batch_is_signed_by_its_own_voter() {
+        let organizer = TestKey::new(100);
+        let (alice, bob) = (TestKey::new(1), TestKey::new(2));
+        let metadata = fund_metadata(&organizer).build().ok().unwrap();
+        let mut tx = ballot_batch_tx(&metadata, &organizer, &[&alice, &bob]);
+        
+        sign_batch(&mut tx, &[&alice, &bob]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // Bob's entry signs something other than his ballot
+        let wrong = ballot_signing_message(&tx.hash(), &EVENT, 1);
+        tx.witnesses[1] = mock_chain::witness_args(&bob.entry(&wrong));
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSignature.code());
     }
     
-    // 2. ELIGIBILITY CHECK
-    match metadata.eligibility_mode {
-        ELIGIBILITY_PUBLIC => {
-            // Public mode: anyone can vote, just verify they have valid signature
-            let mut witness_buf = [0u8; 256];
-            if load_witness(&mut witness_buf, 0).is_err() {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-            
-            let tx_hash = compute_tx_hash();
-            
-            // Verify voter signature
-            let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
-                Ok(arr) => arr,
-                Err(_) => return ERROR_VOTER_INELIGIBLE,
-            };
-            
-            if !verify_signature_by_hash(
-                &voter_hash_array,
-                &witness_buf,
-                &tx_hash,
-            ) {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-        }
-        ELIGIBILITY_INVITE_KEY => {
-            // Invite key mode: verify voter has valid invite signature
-            let mut witness_buf = [0u8; 512];
-            if load_witness(&mut witness_buf, 0).is_err() {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-            
-            // Witness format: [voter_sig: 97 bytes][invite_sig: 97 bytes]
-            if witness_buf.len() < 194 {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-            
-            let tx_hash = compute_tx_hash();
-            
-            // Verify voter signature
-            let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
-                Ok(arr) => arr,
-                Err(_) => return ERROR_VOTER_INELIGIBLE,
-            };
-            
-            if !verify_signature_by_hash(
-                &voter_hash_array,
-                &witness_buf[0..97],
-                &tx_hash,
-            ) {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-            
-            // Verify invite key signature
-            // In production: load invite key from metadata and verify
-            // For now: check that invite signature is present and non-zero
-            if !verify_signature_by_hash(
-                &metadata.organizer_lock_hash, // Use organizer as invite issuer
-                &witness_buf[97..194],
-                &tx_hash,
-            ) {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-        }
-        ELIGIBILITY_CURATED_LIST => {
-            // Curated list: verify voter is in approved list
-            let mut metadata_buf = [0u8; 1024];
-            let metadata_index = match find_metadata_cell(event_id) {
-                Ok(i) => i,
-                Err(e) => return e,
-            };
-            
-            if load_cell_dep_by_field(&mut metadata_buf, metadata_index, CELL_FIELD_DATA).is_err() {
-                return ERROR_METADATA_NOT_FOUND;
-            }
-            
-            // In production: parse voter list from metadata and verify membership
-            // This would typically use a Merkle tree for efficient verification
-            // For now: verify voter has valid signature
-            let mut witness_buf = [0u8; 256];
-            if load_witness(&mut witness_buf, 0).is_err() {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-            
-            let tx_hash = compute_tx_hash();
-            let voter_hash_array: [u8; PUBKEY_HASH_SIZE] = match voter_hash.try_into() {
-                Ok(arr) => arr,
-                Err(_) => return ERROR_VOTER_INELIGIBLE,
-            };
-            
-            if !verify_signature_by_hash(
-                &voter_hash_array,
-                &witness_buf,
-                &tx_hash,
-            ) {
-                return ERROR_VOTER_INELIGIBLE;
-            }
-        }
-        _ => {
-            return ERROR_INVALID_ARGS;
+    // Release fixtures: the result seed is created in RELEASE_BLOCK, once
+    // voting has closed
+    
+    const RELEASE_BLOCK: u64 = 10;
+    
+    /// Two uncapped options, released by `required` of `signers`
+    fn release_metadata(signers: &[&TestKey], required: u8) -> EventMetadataBuilder {
+        let mut builder = EventMetadataBuilder::new(EVENT, [0x0A; PUBKEY_HASH_SIZE])
+            .schedule(VOTING_START, VOTING_END, AUDIT_END)
+            .required_signatures(required)
+            .option_caps(&[0, 0]);
+        for signer in signers {
+            builder = builder.signer(signer.hash());
         }
+        builder
     }
     
-    // 3. REVOTING LIMIT CHECK
-    if metadata.max_revotes < 255 {
-        // Only enforce if not unlimited (255 = unlimited)
-        let previous_count = match count_voter_ballots(event_id, voter_hash) {
-            Ok(c) => c,
-            Err(e) => return e,
-        };
-        
-        if previous_count >= metadata.max_revotes as u32 {
-            return ERROR_REVOTE_LIMIT_EXCEEDED;
-        }
+    /// A ballot for `option`, salted by its voter and sequence number
+    struct Ballot {
+        voter: [u8; PUBKEY_HASH_SIZE],
+        sequence: u32,
+        option: u8,
     }
     
-    // 4. VERIFY EVENTFUND IS PAYING
-    // Check that an EventFund cell for this event is in inputs
-    let mut found_eventfund = false;
-    let mut buf = [0u8; 512];
+    fn ballot(voter: u8, sequence: u32, option: u8) -> Ballot {
+        Ballot { voter: [voter; PUBKEY_HASH_SIZE], sequence, option }
+    }
     
-    for i in 0..16 {
-        if load_input_by_field(&mut buf, i, CELL_FIELD_DATA).is_err() {
-            break;
+    impl Ballot {
+        fn reveal_entry(&self) -> [u8; TALLY_REVEAL_ENTRY_SIZE] {
+            let mut entry = [0u8; TALLY_REVEAL_ENTRY_SIZE];
+            entry[0] = self.option;
+            entry[1..1 + PUBKEY_HASH_SIZE].copy_from_slice(&self.voter);
+            entry[1 + PUBKEY_HASH_SIZE..5 + PUBKEY_HASH_SIZE].copy_from_slice(&self.sequence.to_le_bytes());
+            entry
         }
         
-        if buf[0] == EVENTFUND_TYPE && bytes_equal(&buf[1..33], event_id) {
-            found_eventfund = true;
-            break;
+        fn cell(&self) -> MockCell {
+            let mut commitment = [0u8; BLAKE2B_HASH_SIZE];
+            blake2b_hash(&self.reveal_entry(), &mut commitment);
+            let data = ballot_data(&EVENT, &self.voter, self.sequence, VOTING_START + 1, &commitment);
+            MockCell::new(lock(VOTER_TYPE, &EVENT, Some(&self.voter)), data).created_in(BALLOT_BLOCK)
         }
     }
     
-    if !found_eventfund {
-        return ERROR_EVENTFUND_MISUSE;
+    fn result_data(total_votes: u32, sig_count: u8, tallies: &[u32]) -> std::vec::Vec<u8> {
+        let mut data = std::vec![RESULT_TYPE];
+        data.extend_from_slice(&EVENT);
+        data.extend_from_slice(&total_votes.to_le_bytes());
+        data.push(sig_count);
+        data.push(tallies.len() as u8);
+        for tally in tallies {
+            data.extend_from_slice(&tally.to_le_bytes());
+        }
+        data
     }
     
-    SUCCESS
-}
-
-/// Verify result release
-fn verify_result_release(event_id: &[u8]) -> i8 {
-    // Load metadata
-    let metadata = match load_metadata(event_id) {
-        Ok(m) => m,
-        Err(e) => return e,
-    };
+    /// An unsigned release of `ballots` publishing `tallies`
+    ///
+    /// The result cell claims one vote per distinct voter and `sig_count`
+    /// signatures; `sign_release` must then attach that many.
+    fn release_tx(metadata: &[u8], ballots: &[Ballot], tallies: &[u32], sig_count: u8) -> MockTx {
+        let mut voters: std::vec::Vec<_> = ballots.iter().map(|b| b.voter).collect();
+        voters.sort();
+        voters.dedup();
+        
+        let mut seed = std::vec![RESULT_TYPE];
+        seed.extend_from_slice(&EVENT);
+        
+        let mut tx = MockTx::new(lock(RESULT_TYPE, &EVENT, None));
+        tx.inputs.push(MockCell::new(lock(RESULT_TYPE, &EVENT, None), seed).created_in(RELEASE_BLOCK));
+        tx.inputs.extend(ballots.iter().map(Ballot::cell));
+        tx.cell_deps.push(MockCell::new(mock_chain::script([0x4D; 32], &[]), metadata.to_vec()));
+        tx.header_deps.push(header(BALLOT_BLOCK, VOTING_START + 1));
+        tx.header_deps.push(header(RELEASE_BLOCK, VOTING_END + 1));
+        tx.outputs.push(MockCell::new(
+            lock(RESULT_TYPE, &EVENT, None),
+            result_data(voters.len() as u32, sig_count, tallies),
+        ));
+        tx
+    }
     
-    // Get current timestamp
-    let current_time = match load_current_timestamp() {
-        Ok(t) => t,
-        Err(e) => return e,
-    };
+    /// Sign a release with `signers` and reveal `revealed` after the entries
+    fn sign_release(tx: &mut MockTx, signers: &[&TestKey], revealed: &[&Ballot]) {
+        let message = domain_message(DOMAIN_RELEASE, &tx.hash());
+        let mut witness_lock = std::vec![signers.len() as u8];
+        for signer in signers {
+            witness_lock.extend_from_slice(&signer.entry(&message));
+        }
+        witness_lock.extend_from_slice(&(revealed.len() as u16).to_le_bytes());
+        for ballot in revealed {
+            witness_lock.extend_from_slice(&ballot.reveal_entry());
+        }
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock)];
+    }
     
-    // Check if this is result release (during audit period)
-    // OR result cleanup (after audit period)
-    let is_cleanup = current_time >= metadata.audit_end_time;
+    /// Turnout attestation body for `voter_count` voters over `tx`'s ballots
+    fn attestation_body(tx: &MockTx, voter_count: u32) -> std::vec::Vec<u8> {
+        let root = tx.run(|| compute_ballot_root(&EVENT, BALLOT_PAYLOAD_SIZE)).ok().unwrap();
+        let mut body = std::vec![TURNOUT_ATTESTATION_TYPE];
+        body.extend_from_slice(&EVENT);
+        body.extend_from_slice(&voter_count.to_le_bytes());
+        body.extend_from_slice(&root);
+        body
+    }
     
-    if is_cleanup {
-        // Allow cleanup after audit period with organizer signature
-        let mut witness_buf = [0u8; 256];
-        if load_witness(&mut witness_buf, 0).is_err() {
-            return ERROR_UNAUTHORIZED_WITHDRAWAL;
-        }
+    #[test]
+    fn the_clock_is_the_guarded_cells_block() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
         
-        let tx_hash = compute_tx_hash();
+        // Put the result seed behind an unrelated input from another block,
+        // and attach a header from past the audit period
+        let foreign = MockCell::new(mock_chain::script([0x22; 32], &[]), std::vec![0u8; 8]).created_in(2);
+        tx.inputs.insert(0, foreign);
+        tx.header_deps.push(header(2, VOTING_START + 2));
+        tx.header_deps.push(header(99, AUDIT_END + 1));
+        sign_release(&mut tx, &[&signer], &[&ballots[0]]);
         
-        if !verify_signature_by_hash(
-            &metadata.organizer_lock_hash,
-            &witness_buf,
-            &tx_hash,
-        ) {
-            return ERROR_UNAUTHORIZED_WITHDRAWAL;
-        }
+        assert!(matches!(tx.run(load_current_timestamp), Ok(time) if time == VOTING_END + 1));
+        assert!(matches!(tx.run(load_current_epoch), Ok(RELEASE_BLOCK)));
+        
+        // Still a release, not a cleanup dated by the later header
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // An absolute since on the seed is enforced by consensus, so it can
+        // move the clock forward; a relative one can't
+        let seed = 1;
+        tx.inputs[seed].since = SINCE_METRIC_TIMESTAMP | (AUDIT_END / 1000 + 1);
+        assert!(matches!(tx.run(load_current_timestamp), Ok(time) if time == AUDIT_END + 1000));
+        tx.inputs[seed].since = SINCE_RELATIVE_FLAG | SINCE_METRIC_TIMESTAMP | (AUDIT_END / 1000 + 1);
+        assert!(matches!(tx.run(load_current_timestamp), Ok(time) if time == VOTING_END + 1));
+        tx.inputs[seed].since = SINCE_METRIC_EPOCH | (1 << 40) | 50;
+        assert!(matches!(tx.run(load_current_epoch), Ok(50)));
         
-        return SUCCESS;
+        // Without the seed's own header there is no time to go by
+        tx.header_deps.retain(|h| h.number != RELEASE_BLOCK);
+        assert!(matches!(tx.run(load_current_timestamp), Err(ContractError::HeaderMissing)));
     }
     
-    // 1. TIMELOCK CHECK: Verify voting has ended (results can be decoded)
-    if current_time < metadata.voting_end {
-        return ERROR_TIMELOCK_NOT_EXPIRED;
+    #[test]
+    fn truncated_metadata_cell_is_an_encoding_error() {
+        let signer = TestKey::new(1);
+        let mut metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        metadata.truncate(50);
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
+        sign_release(&mut tx, &[&signer], &[&ballots[0]]);
+        
+        assert!(matches!(tx.run(|| load_metadata(&EVENT)), Err(ContractError::Encoding)));
+        assert_eq!(tx.run(program_entry), ContractError::Encoding.code());
     }
     
-    // 2. MULTISIG CHECK: Verify required signatures
-    let mut witness_buf = [0u8; 2048];
-    let witness_len = match load_witness(&mut witness_buf, 0) {
-        Ok(len) => len,
-        Err(_) => return ERROR_INSUFFICIENT_SIGNATURES,
-    };
+    #[test]
+    fn a_signer_counted_twice_does_not_meet_the_threshold() {
+        let (alice, bob) = (TestKey::new(1), TestKey::new(2));
+        let metadata = release_metadata(&[&alice, &bob], 2).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 2);
+        
+        sign_release(&mut tx, &[&alice, &bob], &[&ballots[0]]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        sign_release(&mut tx, &[&alice, &alice], &[&ballots[0]]);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSignature.code());
+    }
     
-    // Parse signatures from witness
-    // Format: [sig_count: u8][[pubkey: 33][sig: 64]]...
-    if witness_len < 1 {
-        return ERROR_INSUFFICIENT_SIGNATURES;
+    #[test]
+    fn release_signatures_are_not_accepted_as_a_turnout_attestation() {
+        let (alice, bob) = (TestKey::new(1), TestKey::new(2));
+        let metadata = release_metadata(&[&alice, &bob], 2).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 0), ballot(0x12, 0, 1)];
+        
+        let mut tx = release_tx(&metadata, &ballots, &[1, 1], 2);
+        sign_release(&mut tx, &[&alice, &bob], &[&ballots[0], &ballots[1]]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        let release_message = domain_message(DOMAIN_RELEASE, &tx.hash());
+        
+        // An attestation signed over its own tagged body is accepted
+        let body = attestation_body(&tx, 2);
+        let mut preimage = DOMAIN_ATTEST.to_vec();
+        preimage.extend_from_slice(&body);
+        let mut attest_message = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&preimage, &mut attest_message);
+        
+        let mut attested = tx.clone();
+        let mut attestation = body.clone();
+        attestation.push(2);
+        attestation.extend_from_slice(&alice.entry(&attest_message));
+        attestation.extend_from_slice(&bob.entry(&attest_message));
+        attested.cell_deps.push(MockCell::new(mock_chain::script([0x4E; 32], &[]), attestation));
+        sign_release(&mut attested, &[&alice, &bob], &[&ballots[0], &ballots[1]]);
+        assert_eq!(attested.run(program_entry), SUCCESS);
+        
+        // The same signers' release signatures carried over are not
+        let mut replayed = tx.clone();
+        let mut attestation = body;
+        attestation.push(2);
+        attestation.extend_from_slice(&alice.entry(&release_message));
+        attestation.extend_from_slice(&bob.entry(&release_message));
+        replayed.cell_deps.push(MockCell::new(mock_chain::script([0x4E; 32], &[]), attestation));
+        sign_release(&mut replayed, &[&alice, &bob], &[&ballots[0], &ballots[1]]);
+        assert_eq!(replayed.run(program_entry), ContractError::InvalidSignature.code());
     }
     
-    let sig_count = witness_buf[0];
+    #[test]
+    fn a_release_creates_exactly_one_result_cell() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
+        sign_release(&mut tx, &[&signer], &[&ballots[0]]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        let mut none = tx.clone();
+        none.outputs.clear();
+        sign_release(&mut none, &[&signer], &[&ballots[0]]);
+        assert_eq!(none.run(program_entry), ContractError::InvalidTally.code());
+        
+        let mut two = tx.clone();
+        two.outputs.push(two.outputs[0].clone());
+        sign_release(&mut two, &[&signer], &[&ballots[0]]);
+        assert_eq!(two.run(program_entry), ContractError::DuplicateResult.code());
+    }
     
-    if sig_count < metadata.required_signatures {
-        return ERROR_INSUFFICIENT_SIGNATURES;
+    #[test]
+    fn only_each_voters_latest_ballot_is_tallied() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 0), ballot(0x11, 1, 1), ballot(0x12, 0, 1)];
+        
+        let mut tx = release_tx(&metadata, &ballots, &[0, 2], 1);
+        sign_release(&mut tx, &[&signer], &[&ballots[1], &ballots[2]]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // The superseded ballot has no reveal of its own
+        let mut counted = release_tx(&metadata, &ballots, &[1, 2], 1);
+        sign_release(&mut counted, &[&signer], &[&ballots[0], &ballots[1], &ballots[2]]);
+        assert_eq!(counted.run(program_entry), ContractError::InvalidTally.code());
+        
+        // A voter's ballots must be in sequence order, and voters in hash order
+        let reordered = [ballot(0x11, 1, 1), ballot(0x11, 0, 0), ballot(0x12, 0, 1)];
+        let mut tx = release_tx(&metadata, &reordered, &[0, 2], 1);
+        sign_release(&mut tx, &[&signer], &[&reordered[0], &reordered[2]]);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSequence.code());
+        
+        let reordered = [ballot(0x12, 0, 1), ballot(0x11, 0, 0), ballot(0x11, 1, 1)];
+        let mut tx = release_tx(&metadata, &reordered, &[0, 2], 1);
+        sign_release(&mut tx, &[&signer], &[&reordered[0], &reordered[2]]);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSequence.code());
     }
     
-    // Compute message hash for signature verification
-    let tx_hash = compute_tx_hash();
+    #[test]
+    fn a_second_metadata_cell_for_the_event_is_ambiguous() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
+        sign_release(&mut tx, &[&signer], &[&ballots[0]]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // A forged copy naming other signers competes with the real one
+        let forger = TestKey::new(9);
+        let forged = release_metadata(&[&forger], 1).build().ok().unwrap();
+        tx.cell_deps.push(MockCell::new(mock_chain::script([0x4F; 32], &[]), forged));
+        sign_release(&mut tx, &[&signer], &[&ballots[0]]);
+        assert!(matches!(tx.run(|| find_metadata_cell(&EVENT)), Err(ContractError::AmbiguousMetadata)));
+        assert_eq!(tx.run(program_entry), ContractError::AmbiguousMetadata.code());
+    }
     
-    // Verify each signature using production crypto
-    // Load authorized signers from metadata
-    let mut metadata_buf = [0u8; 2048];
-    let metadata_index = match find_metadata_cell(event_id) {
-        Ok(i) => i,
-        Err(e) => return e,
-    };
+    #[test]
+    fn a_release_can_carry_thirty_signatures() {
+        let keys: std::vec::Vec<_> = (1..=30).map(TestKey::new).collect();
+        let signers: std::vec::Vec<_> = keys.iter().collect();
+        let metadata = release_metadata(&signers, 30).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 30);
+        
+        // Far more than one witness buffer's worth of signer entries
+        sign_release(&mut tx, &signers, &[&ballots[0]]);
+        assert!(tx.witnesses[0].len() > 30 * SIGNER_ENTRY_SIZE);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // The last entry, well past the first chunk, is still checked
+        let mut repeated = signers.clone();
+        repeated[29] = signers[0];
+        sign_release(&mut tx, &repeated, &[&ballots[0]]);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSignature.code());
+    }
     
-    if load_cell_dep_by_field(&mut metadata_buf, metadata_index, CELL_FIELD_DATA).is_err() {
-        return ERROR_METADATA_NOT_FOUND;
+    #[test]
+    fn metadata_is_found_past_the_first_sixteen_deps() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
+        
+        for i in 0..20 {
+            let unrelated = MockCell::new(mock_chain::script([0x50 + i; 32], &[]), std::vec![0u8; 40]);
+            tx.cell_deps.insert(0, unrelated);
+        }
+        sign_release(&mut tx, &[&signer], &[&ballots[0]]);
+        
+        assert!(matches!(tx.run(|| find_metadata_cell(&EVENT)), Ok(20)));
+        assert_eq!(tx.run(program_entry), SUCCESS);
     }
     
-    // Parse authorized signers (starts at byte 114 after fixed fields)
-    // Each signer is 20 bytes (pubkey hash)
-    let signers_offset = 114;
+    #[test]
+    fn the_fund_is_frozen_from_voting_end_until_audit_end() {
+        let organizer = TestKey::new(100);
+        let voter = TestKey::new(1);
+        let metadata = fund_metadata(&organizer).build().ok().unwrap();
+        
+        // The last moment of voting still pays for ballots
+        let mut tx = ballot_batch_tx(&metadata, &organizer, &[&voter]);
+        tx.header_deps[0] = header(FUND_BLOCK, VOTING_END);
+        sign_batch(&mut tx, &[&voter]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // Mid-audit neither a ballot nor the organizer can spend it
+        let mid_audit = (VOTING_END + AUDIT_END) / 2;
+        tx.header_deps[0] = header(FUND_BLOCK, mid_audit);
+        sign_batch(&mut tx, &[&voter]);
+        assert_eq!(tx.run(program_entry), ContractError::FundLockedAudit.code());
+        
+        let mut withdrawal = withdrawal_tx(&metadata, &organizer, mid_audit);
+        sign_spend(&mut withdrawal, &organizer, DOMAIN_WITHDRAW);
+        assert_eq!(withdrawal.run(program_entry), ContractError::FundLockedAudit.code());
+        
+        // At audit_end the freeze lifts and withdrawal rules apply
+        let mut withdrawal = withdrawal_tx(&metadata, &organizer, AUDIT_END);
+        sign_spend(&mut withdrawal, &organizer, DOMAIN_WITHDRAW);
+        assert_eq!(withdrawal.run(program_entry), ContractError::ResultsNotFinalized.code());
+    }
     
-    for i in 0..sig_count {
-        let witness_offset = 1 + (i as usize * 97); // 1 byte count + 97 bytes per sig
+    #[test]
+    fn withdrawal_waits_for_a_finalized_result() {
+        let organizer = TestKey::new(100);
+        let metadata = fund_metadata(&organizer).build().ok().unwrap();
         
-        if witness_offset + 97 > witness_len {
-            return ERROR_INSUFFICIENT_SIGNATURES;
-        }
+        // Created while voting was open, spent once the audit period is over
+        let mut tx = withdrawal_tx(&metadata, &organizer, VOTING_START + 2);
+        tx.inputs[0].since = SINCE_METRIC_TIMESTAMP | (AUDIT_END / 1000 + 1);
+        sign_spend(&mut tx, &organizer, DOMAIN_WITHDRAW);
+        assert_eq!(tx.run(program_entry), ContractError::ResultsNotFinalized.code());
         
-        let witness_sig = &witness_buf[witness_offset..witness_offset + 97];
+        // A result cell under some other lock proves nothing
+        let released = result_data(1, 1, &[0, 1]);
+        let mut forged = tx.clone();
+        forged.cell_deps.push(MockCell::new(mock_chain::script([0x51; 32], &[]), released.clone()));
+        sign_spend(&mut forged, &organizer, DOMAIN_WITHDRAW);
+        assert_eq!(forged.run(program_entry), ContractError::ResultsNotFinalized.code());
         
-        // Extract pubkey hash from witness signature
-        let pubkey_from_witness = &witness_sig[0..PUBKEY_SIZE];
-        let mut pubkey_array = [0u8; PUBKEY_SIZE];
-        pubkey_array.copy_from_slice(pubkey_from_witness);
-        let computed_hash = compute_pubkey_hash(&pubkey_array);
+        tx.cell_deps.push(MockCell::new(lock(RESULT_TYPE, &EVENT, None), released));
+        sign_spend(&mut tx, &organizer, DOMAIN_WITHDRAW);
+        assert_eq!(tx.run(program_entry), SUCCESS);
         
-        // Verify this signer is authorized
-        let mut is_authorized = false;
-        for j in 0..10 { // Check up to 10 authorized signers
-            let signer_offset = signers_offset + (j * PUBKEY_HASH_SIZE);
-            
-            if signer_offset + PUBKEY_HASH_SIZE > metadata_buf.len() {
-                break;
-            }
-            
-            let authorized_hash = &metadata_buf[signer_offset..signer_offset + PUBKEY_HASH_SIZE];
-            
-            if bytes_equal(&computed_hash, authorized_hash) {
-                is_authorized = true;
-                break;
-            }
-        }
+        let stranger = TestKey::new(101);
+        sign_spend(&mut tx, &stranger, DOMAIN_WITHDRAW);
+        assert_eq!(tx.run(program_entry), ContractError::SignerMismatch.code());
+    }
+    
+    #[test]
+    fn a_repeated_extension_tag_is_invalid_metadata() {
+        let organizer = TestKey::new(100);
+        let once = fund_metadata(&organizer).build().ok().unwrap();
+        assert!(parse_metadata(&once).is_ok());
         
-        if !is_authorized {
-            return ERROR_INVALID_SIGNATURE;
-        }
+        // A second fee record after the one an auditor would read first
+        let twice = fund_metadata(&organizer)
+            .extension(METADATA_EXT_BALLOT_FEE, &0u64.to_le_bytes())
+            .build()
+            .ok()
+            .unwrap();
+        assert!(matches!(parse_metadata(&twice), Err(ContractError::InvalidMetadata)));
+    }
+    
+    #[test]
+    fn the_recovery_key_sweeps_the_fund_only_after_its_timelock() {
+        let (organizer, recovery) = (TestKey::new(100), TestKey::new(102));
+        let unlock_time = AUDIT_END + 1_000_000;
+        let mut record = recovery.hash().to_vec();
+        record.extend_from_slice(&unlock_time.to_le_bytes());
+        let metadata = fund_metadata(&organizer).extension(METADATA_EXT_RECOVERY, &record).build().ok().unwrap();
         
-        // Verify the signature
-        if !verify_signature_by_hash(
-            &computed_hash,
-            witness_sig,
-            &tx_hash,
-        ) {
-            return ERROR_INVALID_SIGNATURE;
-        }
+        // Past the audit period but short of the recovery unlock
+        let mut tx = withdrawal_tx(&metadata, &organizer, VOTING_START + 2);
+        tx.inputs[0].since = SINCE_METRIC_TIMESTAMP | (AUDIT_END / 1000 + 1);
+        sign_spend(&mut tx, &recovery, DOMAIN_RECOVER);
+        assert_eq!(tx.run(program_entry), ContractError::TimelockNotExpired.code());
+        
+        tx.inputs[0].since = SINCE_METRIC_TIMESTAMP | (unlock_time / 1000);
+        sign_spend(&mut tx, &recovery, DOMAIN_RECOVER);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // Signed for a withdrawal rather than a recovery
+        sign_spend(&mut tx, &recovery, DOMAIN_WITHDRAW);
+        assert_eq!(tx.run(program_entry), ContractError::UnauthorizedWithdrawal.code());
     }
     
-    // 3. K-ANONYMITY CHECK: Verify minimum voters participated
-    if metadata.k_anonymity_threshold > 0 {
-        // Count unique voter cells for this event
-        let mut voter_count = 0u32;
-        let mut buf = [0u8; 512];
+    #[test]
+    fn ballot_change_is_every_fund_output_under_this_lock() {
+        let organizer = TestKey::new(100);
+        let voter = TestKey::new(1);
+        let metadata = fund_metadata(&organizer).build().ok().unwrap();
+        let mut tx = ballot_batch_tx(&metadata, &organizer, &[&voter]);
         
-        for i in 0..1000 {
-            if load_input_by_field(&mut buf, i, CELL_FIELD_DATA).is_err() {
-                break;
-            }
-            
-            if buf[0] == VOTER_TYPE && bytes_equal(&buf[1..33], event_id) {
-                voter_count += 1;
-            }
-        }
+        // Split the change over two fund outputs
+        let change = tx.outputs.pop().unwrap().capacity;
+        tx.outputs.push(fund_cell(&organizer, change / 2));
+        tx.outputs.push(fund_cell(&organizer, change - change / 2));
+        sign_batch(&mut tx, &[&voter]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
         
-        if voter_count < metadata.k_anonymity_threshold as u32 {
-            return ERROR_K_ANONYMITY_VIOLATION;
-        }
+        // A fund output under another lock is not change, so its capacity
+        // has left the fund
+        let last = tx.outputs.len() - 1;
+        tx.outputs[last].lock = mock_chain::script([0x0B; 32], &organizer.hash());
+        sign_batch(&mut tx, &[&voter]);
+        assert_eq!(tx.run(program_entry), ContractError::EventfundMisuse.code());
     }
     
-    // 4. TALLY VERIFICATION: Ensure result matches voter cells
-    // In production: recompute tally from all voter cells and compare
-    // This would decrypt all ballots and verify the tallies match
-    // For MVP: accept if signatures and k-anonymity pass
+    #[test]
+    fn turnout_is_counted_past_two_hundred_fifty_six_voters() {
+        let signer = TestKey::new(1);
+        
+        // 257 voters in hash order, the first of them with a revote
+        let mut ballots: std::vec::Vec<_> = (0..257u32)
+            .map(|i| {
+                let mut voter = [0x11; PUBKEY_HASH_SIZE];
+                voter[..4].copy_from_slice(&i.to_be_bytes());
+                Ballot { voter, sequence: 0, option: 1 }
+            })
+            .collect();
+        ballots.insert(1, Ballot { voter: ballots[0].voter, sequence: 1, option: 1 });
+        
+        let metadata = release_metadata(&[&signer], 1)
+            .k_anonymity_threshold(258)
+            .flags(FLAG_PRIVACY_MODE)
+            .build()
+            .ok()
+            .unwrap();
+        let mut tx = release_tx(&metadata, &ballots, &[0, 257], 1);
+        sign_release(&mut tx, &[&signer], &[]);
+        assert!(matches!(tx.run(|| count_unique_voters(&EVENT)), Ok(257)));
+        
+        // The revote doesn't make a 258th voter
+        assert_eq!(tx.run(program_entry), ContractError::KAnonymityViolation.code());
+    }
     
-    SUCCESS
-}
-
-// ============================================================================
-// Main Entry Point
-// ============================================================================
-
-/// Main entry point
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
-    let result = program_entry();
-    exit(result);
-}
-
-fn program_entry() -> i8 {
-    // Load script arguments
-    let mut args_buf = [0u8; 128];
+    #[test]
+    fn the_result_claims_the_unique_voter_count() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 0), ballot(0x11, 1, 1), ballot(0x12, 0, 1)];
+        
+        let mut tx = release_tx(&metadata, &ballots, &[0, 2], 1);
+        sign_release(&mut tx, &[&signer], &[&ballots[1], &ballots[2]]);
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // Counting the revote as a voter inflates the turnout
+        tx.outputs[0].data = result_data(3, 1, &[0, 2]);
+        sign_release(&mut tx, &[&signer], &[&ballots[1], &ballots[2]]);
+        assert_eq!(tx.run(program_entry), ContractError::InvalidTally.code());
+    }
     
-    let args_len = match load_script_args(&mut args_buf) {
-        Ok(len) => len,
-        Err(e) => return e,
-    };
+    #[test]
+    fn a_ballot_can_be_revoked_until_submissions_close() {
+        let voter = TestKey::new(1);
+        let grace_seconds = 10u32;
+        let metadata = voting_metadata()
+            .extension(METADATA_EXT_SUBMISSION_GRACE, &grace_seconds.to_le_bytes())
+            .build()
+            .ok()
+            .unwrap();
+        let close = VOTING_END + grace_seconds as u64 * 1000;
+        
+        // Spend the ballot without a successor or a fund payment
+        let mut tx = revote_tx(&metadata, &voter, 0, 1);
+        tx.inputs.truncate(1);
+        tx.outputs.clear();
+        
+        for (time, expected) in [
+            (VOTING_END, SUCCESS),
+            (VOTING_END + 1000, SUCCESS),
+            (close, SUCCESS),
+            (close + 1000, ContractError::InvalidTiming.code()),
+        ] {
+            tx.inputs[0].since = SINCE_METRIC_TIMESTAMP | (time / 1000);
+            sign_spend(&mut tx, &voter, DOMAIN_REVOKE);
+            assert_eq!(tx.run(program_entry), expected, "revoked at {}", time);
+        }
+    }
     
-    // Minimum args: 1 byte type + 32 bytes event_id = 33 bytes
-    if args_len < 33 {
-        return ERROR_INVALID_ARGS;
+    #[cfg(feature = "bench")]
+    #[test]
+    fn a_bench_run_reports_cycles_without_changing_the_exit_code() {
+        let keys: std::vec::Vec<_> = (1..=15).map(TestKey::new).collect();
+        let signers: std::vec::Vec<_> = keys.iter().collect();
+        let metadata = release_metadata(&signers, 15).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 15);
+        sign_release(&mut tx, &signers, &[&ballots[0]]);
+        
+        let (code, lines) = tx.run_with_debug(|| {
+            let code = program_entry();
+            bench_report();
+            code
+        });
+        assert_eq!(code, SUCCESS);
+        
+        // Other tests share the counters, so only the shape is fixed
+        assert_eq!(lines.len(), BENCH_PHASE_COUNT);
+        for (line, name) in lines.iter().zip(BENCH_PHASE_NAMES) {
+            let prefix = std::format!("bench {}: ", core::str::from_utf8(name).ok().unwrap());
+            let cycles = line.strip_prefix(&prefix).unwrap();
+            assert!(cycles.parse::<u64>().is_ok());
+        }
+        assert!(!lines[0].ends_with(": 0"));
     }
     
-    let cell_type = args_buf[0];
-    let event_id = &args_buf[1..33];
+    #[test]
+    fn oversized_chain_predecessors_and_reveals_are_rejected() {
+        let signer = TestKey::new(1);
+        let metadata = release_metadata(&[&signer], 1).build().ok().unwrap();
+        let ballots = [ballot(0x11, 0, 1)];
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
+        
+        // A successor linking to the result seed it replaces
+        let mut seed_hash = [0u8; BLAKE2B_HASH_SIZE];
+        blake2b_hash(&tx.inputs[0].data, &mut seed_hash);
+        let mut successor = result_data(1, 1, &[0, 1]);
+        successor.extend_from_slice(&seed_hash);
+        assert!(tx.run(|| verify_chain_link(successor.len(), &successor)).is_ok());
+        
+        tx.inputs[0].data.resize(MAX_CHAINED_CELL_SIZE + 1, 0);
+        let result = tx.run(|| verify_chain_link(successor.len(), &successor));
+        assert!(matches!(result, Err(ContractError::BrokenChain)));
+        
+        // A reveal padded past what the release can load
+        let mut tx = release_tx(&metadata, &ballots, &[0, 1], 1);
+        let message = domain_message(DOMAIN_RELEASE, &tx.hash());
+        let mut witness_lock = std::vec![1];
+        witness_lock.extend_from_slice(&signer.entry(&message));
+        witness_lock.extend_from_slice(&1u16.to_le_bytes());
+        witness_lock.extend_from_slice(&ballots[0].reveal_entry());
+        witness_lock.resize(1 + SIGNER_ENTRY_SIZE + MAX_REVEAL_SIZE + 1, 0);
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock)];
+        assert_eq!(tx.run(program_entry), ContractError::InvalidReveal.code());
+    }
     
-    // Additional args for voter cells (voter pubkey hash)
-    let voter_hash = if args_len >= 53 {
-        &args_buf[33..53]
-    } else {
-        &[0u8; 20]
-    };
+    #[test]
+    fn invite_witnesses_hold_the_voter_entry_then_the_invite() {
+        let (voter, issuer) = (TestKey::new(1), TestKey::new(103));
+        let metadata = voting_metadata()
+            .eligibility_mode(ELIGIBILITY_INVITE_KEY)
+            .extension(METADATA_EXT_INVITE_KEY, &issuer.hash())
+            .build()
+            .ok()
+            .unwrap();
+        let mut tx = revote_tx(&metadata, &voter, 0, 1);
+        let ballot_message = ballot_signing_message(&tx.hash(), &EVENT, 1);
+        let invite_approval = domain_message(DOMAIN_INVITE, &tx.hash());
+        
+        let mut witness_lock = voter.entry(&ballot_message).to_vec();
+        witness_lock.extend_from_slice(&issuer.entry(&invite_approval));
+        assert_eq!(witness_lock.len(), INVITE_WITNESS_SIZE);
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock)];
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // The invite alone, without room for the voter's entry
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock[..SIGNER_ENTRY_SIZE])];
+        assert_eq!(tx.run(program_entry), ContractError::VoterIneligible.code());
+        
+        // An invite approved by someone other than the issuer
+        witness_lock.truncate(SIGNER_ENTRY_SIZE);
+        witness_lock.extend_from_slice(&voter.entry(&invite_approval));
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock)];
+        assert_eq!(tx.run(program_entry), ContractError::SignerMismatch.code());
+    }
     
-    // Route to appropriate validation based on cell type
-    match cell_type {
-        EVENTFUND_TYPE => verify_eventfund(event_id),
-        METADATA_TYPE => verify_metadata(event_id),
-        VOTER_TYPE => verify_voter_ballot(event_id, voter_hash),
-        RESULT_TYPE => verify_result_release(event_id),
-        _ => ERROR_INVALID_ARGS,
+    #[test]
+    fn aggregate_invites_follow_the_voter_entry_in_one_witness() {
+        let voter = TestKey::new(1);
+        let mut aggregate_pubkey = [0u8; BLS_PUBKEY_SIZE];
+        aggregate_pubkey[0] = 0x80;
+        let metadata = voting_metadata()
+            .eligibility_mode(ELIGIBILITY_INVITE_BLS)
+            .extension(METADATA_EXT_BLS_AGGREGATE_PUBKEY, &aggregate_pubkey)
+            .build()
+            .ok()
+            .unwrap();
+        let mut tx = revote_tx(&metadata, &voter, 0, 1);
+        let ballot_message = ballot_signing_message(&tx.hash(), &EVENT, 1);
+        
+        let mut aggregate_sig = [0u8; BLS_SIGNATURE_SIZE];
+        aggregate_sig[0] = 0x80;
+        let mut witness_lock = voter.entry(&ballot_message).to_vec();
+        witness_lock.extend_from_slice(&aggregate_sig);
+        assert_eq!(witness_lock.len(), BLS_INVITE_WITNESS_SIZE);
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock)];
+        assert_eq!(tx.run(program_entry), SUCCESS);
+        
+        // A voter entry with no aggregate after it
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock[..SIGNER_ENTRY_SIZE])];
+        assert_eq!(tx.run(program_entry), ContractError::VoterIneligible.code());
+        
+        // The voter's entry is still checked first
+        let stale = ballot_signing_message(&tx.hash(), &EVENT, 0);
+        witness_lock[..SIGNER_ENTRY_SIZE].copy_from_slice(&voter.entry(&stale));
+        tx.witnesses = std::vec![mock_chain::witness_args(&witness_lock)];
+        assert_eq!(tx.run(program_entry), ContractError::InvalidSignature.code());
     }
 }
-
-// ============================================================================
-// Panic Handler
-// ============================================================================
-
-#[panic_handler]
-fn panic_handler(_: &core::panic::PanicInfo) -> ! {
-    exit(ERROR_ENCODING);
-}
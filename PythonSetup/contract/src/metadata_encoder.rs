@@ -0,0 +1,291 @@
+//! Metadata cell encoder for integrators
+//!
+//! Serializes an `EventMetadataBuilder` into the exact byte layout that
+//! `parse_metadata` and `MetadataReader` decode, so off-chain tooling never
+//! has to hand-assemble offsets. Only built with the `std` feature; the
+//! on-chain script never encodes metadata.
+
+// Nothing in the script calls the encoder
+#![allow(dead_code)]
+
+use std::vec::Vec;
+
+use crate::{
+    ContractError, BLAKE2B_HASH_SIZE, ELIGIBILITY_CURATED_LIST, ELIGIBILITY_PUBLIC, EVENT_ID_SIZE,
+    MAX_AUTHORIZED_SIGNERS, MAX_METADATA_SIZE, MAX_VOTE_OPTIONS, METADATA_EXT_FLAGS, METADATA_TYPE,
+    PUBKEY_HASH_SIZE,
+};
+
+/// Event configuration to encode as a metadata cell
+pub struct EventMetadataBuilder {
+    event_id: [u8; EVENT_ID_SIZE],
+    organizer_lock_hash: [u8; PUBKEY_HASH_SIZE],
+    voting_start: u64,
+    voting_end: u64,
+    audit_end_time: u64,
+    eligibility_mode: u8,
+    max_revotes: u8,
+    required_signatures: u8,
+    k_anonymity_threshold: u16,
+    frontend_code_hash: [u8; BLAKE2B_HASH_SIZE],
+    // Curated list leaves, only written in curated list mode
+    eligibility_data: Vec<u8>,
+    signers: Vec<[u8; PUBKEY_HASH_SIZE]>,
+    option_caps: Vec<u32>,
+    // Extension records as (tag, value), written in order
+    extensions: Vec<(u8, Vec<u8>)>,
+}
+
+impl EventMetadataBuilder {
+    /// Start a public event with no signers, caps or extensions
+    pub fn new(event_id: [u8; EVENT_ID_SIZE], organizer_lock_hash: [u8; PUBKEY_HASH_SIZE]) -> Self {
+        EventMetadataBuilder {
+            event_id,
+            organizer_lock_hash,
+            voting_start: 0,
+            voting_end: 0,
+            audit_end_time: 0,
+            eligibility_mode: ELIGIBILITY_PUBLIC,
+            max_revotes: 0,
+            required_signatures: 0,
+            k_anonymity_threshold: 0,
+            frontend_code_hash: [0u8; BLAKE2B_HASH_SIZE],
+            eligibility_data: Vec::new(),
+            signers: Vec::new(),
+            option_caps: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+    
+    pub fn schedule(mut self, voting_start: u64, voting_end: u64, audit_end_time: u64) -> Self {
+        self.voting_start = voting_start;
+        self.voting_end = voting_end;
+        self.audit_end_time = audit_end_time;
+        self
+    }
+    
+    pub fn eligibility_mode(mut self, mode: u8) -> Self {
+        self.eligibility_mode = mode;
+        self
+    }
+    
+    /// Switch to curated list mode with the given (voter_hash, max_weight) leaves
+    pub fn curated_list(mut self, leaves: &[([u8; PUBKEY_HASH_SIZE], u32)]) -> Self {
+        self.eligibility_mode = ELIGIBILITY_CURATED_LIST;
+        self.eligibility_data.clear();
+        for (voter_hash, max_weight) in leaves {
+            self.eligibility_data.extend_from_slice(voter_hash);
+            self.eligibility_data.extend_from_slice(&max_weight.to_le_bytes());
+        }
+        self
+    }
+    
+    pub fn max_revotes(mut self, max_revotes: u8) -> Self {
+        self.max_revotes = max_revotes;
+        self
+    }
+    
+    pub fn required_signatures(mut self, required_signatures: u8) -> Self {
+        self.required_signatures = required_signatures;
+        self
+    }
+    
+    pub fn k_anonymity_threshold(mut self, k: u16) -> Self {
+        self.k_anonymity_threshold = k;
+        self
+    }
+    
+    pub fn frontend_code_hash(mut self, hash: [u8; BLAKE2B_HASH_SIZE]) -> Self {
+        self.frontend_code_hash = hash;
+        self
+    }
+    
+    /// Append an authorized result-release signer
+    pub fn signer(mut self, lock_hash: [u8; PUBKEY_HASH_SIZE]) -> Self {
+        self.signers.push(lock_hash);
+        self
+    }
+    
+    /// Set per-option vote caps (0 = uncapped option)
+    pub fn option_caps(mut self, caps: &[u32]) -> Self {
+        self.option_caps = caps.to_vec();
+        self
+    }
+    
+    /// Set the metadata flags (METADATA_EXT_FLAGS record)
+    pub fn flags(self, flags: u32) -> Self {
+        self.extension(METADATA_EXT_FLAGS, &flags.to_le_bytes())
+    }
+    
+    /// Append a raw extension record
+    pub fn extension(mut self, tag: u8, value: &[u8]) -> Self {
+        self.extensions.push((tag, value.to_vec()));
+        self
+    }
+    
+    /// Serialize into metadata cell data
+    ///
+    /// Only the layout is checked here; schedule and configuration rules
+    /// are left to `parse_metadata`, which the output should be run through.
+    pub fn build(&self) -> Result<Vec<u8>, ContractError> {
+        if self.signers.len() > MAX_AUTHORIZED_SIGNERS || self.option_caps.len() > MAX_VOTE_OPTIONS {
+            return Err(ContractError::Encoding);
+        }
+        if self.eligibility_data.len() > u16::MAX as usize {
+            return Err(ContractError::Encoding);
+        }
+        if self.eligibility_mode != ELIGIBILITY_CURATED_LIST && !self.eligibility_data.is_empty() {
+            return Err(ContractError::Encoding);
+        }
+        
+        let mut data = Vec::new();
+        
+        // Fixed core
+        data.push(METADATA_TYPE);
+        data.extend_from_slice(&self.event_id);
+        data.extend_from_slice(&self.organizer_lock_hash);
+        data.extend_from_slice(&self.voting_start.to_le_bytes());
+        data.extend_from_slice(&self.voting_end.to_le_bytes());
+        data.extend_from_slice(&self.audit_end_time.to_le_bytes());
+        data.push(self.eligibility_mode);
+        data.push(self.max_revotes);
+        data.push(self.required_signatures);
+        data.extend_from_slice(&self.k_anonymity_threshold.to_le_bytes());
+        data.extend_from_slice(&self.frontend_code_hash);
+        
+        // Curated eligibility block, only present in curated list mode
+        if self.eligibility_mode == ELIGIBILITY_CURATED_LIST {
+            data.extend_from_slice(&(self.eligibility_data.len() as u16).to_le_bytes());
+            data.extend_from_slice(&self.eligibility_data);
+        }
+        
+        data.push(self.signers.len() as u8);
+        for signer in &self.signers {
+            data.extend_from_slice(signer);
+        }
+        
+        // Caps are optional, but the count must be written whenever
+        // extensions follow so the parser can find where they start
+        if !self.option_caps.is_empty() || !self.extensions.is_empty() {
+            data.push(self.option_caps.len() as u8);
+            for cap in &self.option_caps {
+                data.extend_from_slice(&cap.to_le_bytes());
+            }
+        }
+        
+        for (tag, value) in &self.extensions {
+            if value.len() > u8::MAX as usize {
+                return Err(ContractError::Encoding);
+            }
+            data.push(*tag);
+            data.push(value.len() as u8);
+            data.extend_from_slice(value);
+        }
+        
+        // The script refuses to load anything larger
+        if data.len() > MAX_METADATA_SIZE {
+            return Err(ContractError::Encoding);
+        }
+        
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parse_metadata, MetadataReader, FLAG_INSTANT_FINALIZE, FLAG_PRIVACY_MODE, METADATA_EXT_ELIGIBLE_VOTERS,
+    };
+    
+    fn builder() -> EventMetadataBuilder {
+        EventMetadataBuilder::new([0xAA; EVENT_ID_SIZE], [0xBB; PUBKEY_HASH_SIZE])
+            .schedule(1_000, 2_000, 3_000)
+            .max_revotes(2)
+            .signer([0x01; PUBKEY_HASH_SIZE])
+            .signer([0x02; PUBKEY_HASH_SIZE])
+            .required_signatures(2)
+    }
+    
+    #[test]
+    fn encoded_metadata_parses_back() {
+        let data = builder()
+            .k_anonymity_threshold(5)
+            .frontend_code_hash([0xCC; BLAKE2B_HASH_SIZE])
+            .option_caps(&[10, 0, 7])
+            .flags(FLAG_PRIVACY_MODE)
+            .build()
+            .ok()
+            .unwrap();
+        let metadata = parse_metadata(&data).ok().unwrap();
+        
+        assert!(metadata.event_id == [0xAA; EVENT_ID_SIZE]);
+        assert!(metadata.organizer_lock_hash == [0xBB; PUBKEY_HASH_SIZE]);
+        assert!((metadata.voting_start, metadata.voting_end, metadata.audit_end_time) == (1_000, 2_000, 3_000));
+        assert!(metadata.eligibility_mode == ELIGIBILITY_PUBLIC);
+        assert!(metadata.max_revotes == 2 && metadata.required_signatures == 2);
+        assert!(metadata.k_anonymity_threshold == 5 && metadata.flags == FLAG_PRIVACY_MODE);
+        assert!(metadata.frontend_code_hash == [0xCC; BLAKE2B_HASH_SIZE]);
+        
+        let reader = MetadataReader::new(&data, data.len()).ok().unwrap();
+        assert!(reader.signer_count() == 2);
+        assert!(matches!(reader.signer(1), Ok(signer) if signer == [0x02; PUBKEY_HASH_SIZE]));
+        assert!(reader.option_cap_count() == 3);
+        assert!(matches!(reader.option_cap(2), Ok(7)));
+    }
+    
+    #[test]
+    fn curated_list_round_trips_through_the_eligibility_block() {
+        let leaves = [([0x11; PUBKEY_HASH_SIZE], 3), ([0x22; PUBKEY_HASH_SIZE], 1)];
+        let data = builder()
+            .curated_list(&leaves)
+            .schedule(1_000, 2_000, 2_000)
+            .flags(FLAG_INSTANT_FINALIZE)
+            .extension(METADATA_EXT_ELIGIBLE_VOTERS, &2u32.to_le_bytes())
+            .build()
+            .ok()
+            .unwrap();
+        let metadata = parse_metadata(&data).ok().unwrap();
+        assert!(metadata.eligibility_mode == ELIGIBILITY_CURATED_LIST && metadata.eligible_voter_count == 2);
+        
+        let reader = MetadataReader::new(&data, data.len()).ok().unwrap();
+        let eligibility_data = reader.eligibility_data();
+        assert!(eligibility_data.len() == 2 * (PUBKEY_HASH_SIZE + 4));
+        assert!(eligibility_data[PUBKEY_HASH_SIZE..PUBKEY_HASH_SIZE + 4] == 3u32.to_le_bytes());
+        assert!(reader.signer_count() == 2);
+    }
+    
+    #[test]
+    fn instant_finalize_needs_a_curated_list_of_the_declared_size() {
+        let public = builder()
+            .schedule(1_000, 2_000, 2_000)
+            .flags(FLAG_INSTANT_FINALIZE)
+            .extension(METADATA_EXT_ELIGIBLE_VOTERS, &2u32.to_le_bytes())
+            .build()
+            .ok()
+            .unwrap();
+        assert!(matches!(parse_metadata(&public), Err(ContractError::InvalidTiming)));
+        
+        let understated = builder()
+            .curated_list(&[([0x11; PUBKEY_HASH_SIZE], 1), ([0x22; PUBKEY_HASH_SIZE], 1)])
+            .schedule(1_000, 2_000, 2_000)
+            .flags(FLAG_INSTANT_FINALIZE)
+            .extension(METADATA_EXT_ELIGIBLE_VOTERS, &1u32.to_le_bytes())
+            .build()
+            .ok()
+            .unwrap();
+        assert!(matches!(parse_metadata(&understated), Err(ContractError::InvalidTiming)));
+    }
+    
+    #[test]
+    fn builder_rejects_layouts_the_script_cant_load() {
+        let mut too_many_signers = builder();
+        for i in 0..=MAX_AUTHORIZED_SIGNERS {
+            too_many_signers = too_many_signers.signer([i as u8; PUBKEY_HASH_SIZE]);
+        }
+        assert!(too_many_signers.build().is_err());
+        
+        assert!(builder().option_caps(&[1; MAX_VOTE_OPTIONS + 1]).build().is_err());
+        assert!(builder().extension(0x7F, &[0; 256]).build().is_err());
+    }
+}
@@ -0,0 +1,440 @@
+//! Host-side stand-in for the CKB VM, used by the unit tests
+//!
+//! A test describes a transaction as a `MockTx` and runs script code
+//! against it with `MockTx::run`. Every syscall the script makes is then
+//! answered from that transaction with the VM's partial-load semantics,
+//! so loaders, scans and whole validation paths run unchanged on the host.
+
+use std::cell::{Cell, RefCell};
+use std::string::String;
+use std::vec::Vec;
+
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+
+use crate::{
+    blake2b_hash, compute_pubkey_hash, BLAKE2B_HASH_SIZE, CELL_FIELD_CAPACITY, CELL_FIELD_DATA_HASH,
+    CELL_FIELD_LOCK, CELL_FIELD_LOCK_HASH, CELL_FIELD_OCCUPIED_CAPACITY, CELL_FIELD_TYPE_HASH,
+    HEADER_FIELD_EPOCH_NUMBER, HEADER_FIELD_TIMESTAMP, INPUT_FIELD_SINCE, PUBKEY_HASH_SIZE, PUBKEY_SIZE,
+    SCRIPT_HEADER_SIZE, SIGNATURE_SIZE, SOURCE_CELL_DEP, SOURCE_GROUP_INPUT, SOURCE_GROUP_OUTPUT,
+    SOURCE_INPUT, SOURCE_OUTPUT, SYSCALL_INDEX_OUT_OF_BOUND, SYSCALL_ITEM_MISSING, SYS_EXIT,
+    SYS_LOAD_CELL_BY_FIELD, SYS_LOAD_CELL_DATA, SYS_LOAD_HEADER_BY_FIELD, SYS_LOAD_INPUT_BY_FIELD,
+    SYS_LOAD_SCRIPT, SYS_LOAD_TX_HASH, SYS_LOAD_WITNESS,
+};
+#[cfg(feature = "bench")]
+use crate::{SYS_CURRENT_CYCLES, SYS_DEBUG};
+
+// Shannons per CKByte, the unit of occupied capacity
+const SHANNONS_PER_BYTE: u64 = 100_000_000;
+
+// Type script field of LOAD_CELL_BY_FIELD; the script itself only asks for its hash
+const CELL_FIELD_TYPE: u64 = 4;
+
+// Header dep source; the script only reads headers through the cells they created
+const SOURCE_HEADER_DEP: u64 = 4;
+
+// Cycles the mock charges for each syscall, so bench phases measure something
+#[cfg(feature = "bench")]
+const CYCLES_PER_SYSCALL: u64 = 1_000;
+
+thread_local! {
+    // Transaction answering syscalls while `MockTx::run` is active
+    static TX: RefCell<Option<MockTx>> = const { RefCell::new(None) };
+    // Lines written through SYS_DEBUG during the current run
+    static DEBUG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static CYCLES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A cell as seen by the script: an input, output or cell dep
+#[derive(Clone)]
+pub struct MockCell {
+    pub capacity: u64,
+    // Molecule `Script` bytes
+    pub lock: Vec<u8>,
+    pub type_script: Option<Vec<u8>>,
+    pub data: Vec<u8>,
+    // Number of the block that created the cell (inputs and cell deps)
+    pub block: Option<u64>,
+    // Since of the spending `CellInput`, for inputs
+    pub since: u64,
+}
+
+impl MockCell {
+    /// A cell holding exactly its occupied capacity
+    pub fn new(lock: Vec<u8>, data: Vec<u8>) -> Self {
+        let mut cell = MockCell { capacity: 0, lock, type_script: None, data, block: None, since: 0 };
+        cell.capacity = cell.occupied_capacity();
+        cell
+    }
+
+    pub fn with_capacity(mut self, capacity: u64) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Attach a type script, growing the capacity to still cover the cell
+    pub fn with_type(mut self, type_script: Vec<u8>) -> Self {
+        self.type_script = Some(type_script);
+        self.capacity = self.capacity.max(self.occupied_capacity());
+        self
+    }
+
+    pub fn created_in(mut self, block: u64) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Occupied capacity as CKB computes it: capacity field, data and scripts
+    pub fn occupied_capacity(&self) -> u64 {
+        let script_size = |script: &[u8]| BLAKE2B_HASH_SIZE + 1 + script_args(script).len();
+        let bytes = 8 + self.data.len() + script_size(&self.lock) + self.type_script.as_deref().map_or(0, script_size);
+        bytes as u64 * SHANNONS_PER_BYTE
+    }
+}
+
+/// A block header that can be listed in `header_deps`
+#[derive(Clone, Copy)]
+pub struct MockHeader {
+    pub number: u64,
+    pub epoch: u64,
+    pub timestamp: u64,
+}
+
+/// The transaction a script instance validates
+///
+/// `script` is the running lock; its group inputs are the inputs locked by
+/// exactly those bytes. Witnesses line up with inputs by index.
+#[derive(Clone, Default)]
+pub struct MockTx {
+    pub script: Vec<u8>,
+    pub inputs: Vec<MockCell>,
+    pub outputs: Vec<MockCell>,
+    pub cell_deps: Vec<MockCell>,
+    pub header_deps: Vec<MockHeader>,
+    pub witnesses: Vec<Vec<u8>>,
+}
+
+impl MockTx {
+    pub fn new(script: Vec<u8>) -> Self {
+        MockTx { script, ..Default::default() }
+    }
+
+    /// Run `f` with every syscall answered from this transaction
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.run_with_debug(f).0
+    }
+
+    /// Like `run`, also returning the lines written through SYS_DEBUG
+    pub fn run_with_debug<T>(&self, f: impl FnOnce() -> T) -> (T, Vec<String>) {
+        TX.with(|tx| *tx.borrow_mut() = Some(self.clone()));
+        DEBUG.with(|debug| debug.borrow_mut().clear());
+        CYCLES.with(|cycles| cycles.set(0));
+
+        let result = f();
+
+        TX.with(|tx| *tx.borrow_mut() = None);
+        (result, DEBUG.with(|debug| debug.take()))
+    }
+
+    /// Hash of the molecule `RawTransaction`, as the VM reports it
+    ///
+    /// Witnesses are not covered, so a test can sign this hash and then add
+    /// the signatures. Out points are derived from the cells they name.
+    pub fn hash(&self) -> [u8; BLAKE2B_HASH_SIZE] {
+        let out_point = |tag: &[u8], cell: &MockCell, index: usize| {
+            let mut preimage = tag.to_vec();
+            preimage.extend_from_slice(&cell.lock);
+            preimage.extend_from_slice(&cell.data);
+            let mut bytes = hash(&preimage).to_vec();
+            bytes.extend_from_slice(&(index as u32).to_le_bytes());
+            bytes
+        };
+
+        let mut cell_deps = Vec::new();
+        for (i, cell) in self.cell_deps.iter().enumerate() {
+            let mut dep = out_point(b"dep", cell, i);
+            dep.push(0);
+            cell_deps.push(dep);
+        }
+        let header_deps: Vec<Vec<u8>> = self.header_deps.iter().map(|h| header_hash(h).to_vec()).collect();
+        let mut inputs = Vec::new();
+        for (i, cell) in self.inputs.iter().enumerate() {
+            let mut input = cell.since.to_le_bytes().to_vec();
+            input.extend_from_slice(&out_point(b"input", cell, i));
+            inputs.push(input);
+        }
+        let outputs: Vec<Vec<u8>> = self
+            .outputs
+            .iter()
+            .map(|cell| {
+                table(&[
+                    cell.capacity.to_le_bytes().to_vec(),
+                    cell.lock.clone(),
+                    cell.type_script.clone().unwrap_or_default(),
+                ])
+            })
+            .collect();
+        let outputs_data: Vec<Vec<u8>> = self.outputs.iter().map(|cell| bytes(&cell.data)).collect();
+
+        let raw = table(&[
+            0u32.to_le_bytes().to_vec(),
+            fixvec(&cell_deps),
+            fixvec(&header_deps),
+            fixvec(&inputs),
+            dynvec(&outputs),
+            dynvec(&outputs_data),
+        ]);
+        hash(&raw)
+    }
+
+    fn group_inputs(&self) -> impl Iterator<Item = (usize, &MockCell)> {
+        self.inputs.iter().enumerate().filter(move |(_, cell)| cell.lock == self.script)
+    }
+
+    fn cell(&self, index: usize, source: u64) -> Option<&MockCell> {
+        match source {
+            SOURCE_INPUT => self.inputs.get(index),
+            SOURCE_OUTPUT => self.outputs.get(index),
+            SOURCE_CELL_DEP => self.cell_deps.get(index),
+            SOURCE_GROUP_INPUT => self.group_inputs().nth(index).map(|(_, cell)| cell),
+            // A lock script group never has outputs
+            SOURCE_GROUP_OUTPUT => None,
+            _ => panic!("unknown cell source {:#x}", source),
+        }
+    }
+
+    fn witness(&self, index: usize, source: u64) -> Option<&Vec<u8>> {
+        match source {
+            SOURCE_INPUT | SOURCE_OUTPUT => self.witnesses.get(index),
+            SOURCE_GROUP_INPUT => self.group_inputs().nth(index).and_then(|(i, _)| self.witnesses.get(i)),
+            _ => panic!("unknown witness source {:#x}", source),
+        }
+    }
+
+    /// Header of a header dep, or of the block that created a cell
+    ///
+    /// `Err` carries the syscall return code.
+    fn header(&self, index: usize, source: u64) -> Result<MockHeader, u64> {
+        if source == SOURCE_HEADER_DEP {
+            return self.header_deps.get(index).copied().ok_or(SYSCALL_INDEX_OUT_OF_BOUND);
+        }
+
+        let cell = self.cell(index, source).ok_or(SYSCALL_INDEX_OUT_OF_BOUND)?;
+        let block = cell.block.ok_or(SYSCALL_ITEM_MISSING)?;
+        self.header_deps.iter().find(|h| h.number == block).copied().ok_or(SYSCALL_ITEM_MISSING)
+    }
+}
+
+/// Answer a syscall from the running mock transaction
+///
+/// # Safety
+///
+/// Pointer arguments must be valid as the VM would require: a writable
+/// buffer at `arg0` of the size stored at `arg1`.
+pub unsafe fn syscall(n: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> u64 {
+    #[cfg(feature = "bench")]
+    CYCLES.with(|cycles| cycles.set(cycles.get() + CYCLES_PER_SYSCALL));
+
+    #[cfg(feature = "bench")]
+    match n {
+        SYS_CURRENT_CYCLES => return CYCLES.with(|cycles| cycles.get()),
+        SYS_DEBUG => {
+            let line = std::ffi::CStr::from_ptr(arg0 as *const core::ffi::c_char);
+            DEBUG.with(|debug| debug.borrow_mut().push(line.to_string_lossy().into_owned()));
+            return 0;
+        }
+        _ => {}
+    }
+
+    TX.with(|tx| {
+        let tx = tx.borrow();
+        let tx = tx.as_ref().expect("syscall outside MockTx::run");
+        let (index, source) = (arg3 as usize, arg4);
+
+        match n {
+            SYS_LOAD_TX_HASH => store(arg0, arg1, arg2, &tx.hash()),
+            SYS_LOAD_SCRIPT => store(arg0, arg1, arg2, &tx.script),
+            SYS_LOAD_WITNESS => match tx.witness(index, source) {
+                Some(witness) => store(arg0, arg1, arg2, witness),
+                None => SYSCALL_INDEX_OUT_OF_BOUND,
+            },
+            SYS_LOAD_CELL_DATA => match tx.cell(index, source) {
+                Some(cell) => store(arg0, arg1, arg2, &cell.data),
+                None => SYSCALL_INDEX_OUT_OF_BOUND,
+            },
+            SYS_LOAD_CELL_BY_FIELD => {
+                let cell = match tx.cell(index, source) {
+                    Some(cell) => cell,
+                    None => return SYSCALL_INDEX_OUT_OF_BOUND,
+                };
+                let field = match arg5 {
+                    CELL_FIELD_CAPACITY => cell.capacity.to_le_bytes().to_vec(),
+                    CELL_FIELD_DATA_HASH => hash(&cell.data).to_vec(),
+                    CELL_FIELD_LOCK => cell.lock.clone(),
+                    CELL_FIELD_LOCK_HASH => hash(&cell.lock).to_vec(),
+                    CELL_FIELD_TYPE => match &cell.type_script {
+                        Some(script) => script.clone(),
+                        None => return SYSCALL_ITEM_MISSING,
+                    },
+                    CELL_FIELD_TYPE_HASH => match &cell.type_script {
+                        Some(script) => hash(script).to_vec(),
+                        None => return SYSCALL_ITEM_MISSING,
+                    },
+                    CELL_FIELD_OCCUPIED_CAPACITY => cell.occupied_capacity().to_le_bytes().to_vec(),
+                    _ => panic!("unknown cell field {}", arg5),
+                };
+                store(arg0, arg1, arg2, &field)
+            }
+            SYS_LOAD_INPUT_BY_FIELD => {
+                let cell = match source {
+                    SOURCE_INPUT | SOURCE_GROUP_INPUT => match tx.cell(index, source) {
+                        Some(cell) => cell,
+                        None => return SYSCALL_INDEX_OUT_OF_BOUND,
+                    },
+                    _ => panic!("inputs only have input fields"),
+                };
+                match arg5 {
+                    INPUT_FIELD_SINCE => store(arg0, arg1, arg2, &cell.since.to_le_bytes()),
+                    _ => panic!("unknown input field {}", arg5),
+                }
+            }
+            SYS_LOAD_HEADER_BY_FIELD => {
+                let header = match tx.header(index, source) {
+                    Ok(header) => header,
+                    Err(code) => return code,
+                };
+                let value = match arg5 {
+                    HEADER_FIELD_EPOCH_NUMBER => header.epoch,
+                    HEADER_FIELD_TIMESTAMP => header.timestamp,
+                    _ => panic!("unknown header field {}", arg5),
+                };
+                store(arg0, arg1, arg2, &value.to_le_bytes())
+            }
+            SYS_EXIT => panic!("script exited with {}", arg0 as i8),
+            _ => panic!("unsupported syscall {}", n),
+        }
+    })
+}
+
+/// Copy `bytes[offset..]` into the caller's buffer like the VM does
+///
+/// At most the buffer size stored at `len_ptr` is copied, and the full
+/// length from `offset` is written back so truncation can be detected.
+unsafe fn store(addr: u64, len_ptr: u64, offset: u64, bytes: &[u8]) -> u64 {
+    let len_ptr = len_ptr as *mut u64;
+    let offset = (offset as usize).min(bytes.len());
+    let available = &bytes[offset..];
+    let copied = available.len().min(*len_ptr as usize);
+
+    core::ptr::copy_nonoverlapping(available.as_ptr(), addr as *mut u8, copied);
+    *len_ptr = available.len() as u64;
+    0
+}
+
+fn hash(data: &[u8]) -> [u8; BLAKE2B_HASH_SIZE] {
+    let mut out = [0u8; BLAKE2B_HASH_SIZE];
+    blake2b_hash(data, &mut out);
+    out
+}
+
+fn header_hash(header: &MockHeader) -> [u8; BLAKE2B_HASH_SIZE] {
+    let mut preimage = header.number.to_le_bytes().to_vec();
+    preimage.extend_from_slice(&header.epoch.to_le_bytes());
+    preimage.extend_from_slice(&header.timestamp.to_le_bytes());
+    hash(&preimage)
+}
+
+fn script_args(script: &[u8]) -> &[u8] {
+    &script[SCRIPT_HEADER_SIZE..]
+}
+
+// ============================================================================
+// Molecule Encoding
+// ============================================================================
+
+/// Molecule table: [total_size][field offsets][fields]
+fn table(fields: &[Vec<u8>]) -> Vec<u8> {
+    let header_size = 4 * (fields.len() + 1);
+    let total = header_size + fields.iter().map(Vec::len).sum::<usize>();
+
+    let mut out = (total as u32).to_le_bytes().to_vec();
+    let mut offset = header_size;
+    for field in fields {
+        out.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    for field in fields {
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+/// Molecule fixvec of fixed-size items: [item_count][items]
+fn fixvec(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = (items.len() as u32).to_le_bytes().to_vec();
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+/// Molecule `Bytes`, a fixvec of single bytes
+fn bytes(data: &[u8]) -> Vec<u8> {
+    let mut out = (data.len() as u32).to_le_bytes().to_vec();
+    out.extend_from_slice(data);
+    out
+}
+
+/// Molecule dynvec, which has the same layout as a table
+fn dynvec(items: &[Vec<u8>]) -> Vec<u8> {
+    table(items)
+}
+
+/// Molecule `Script` with a type hash_type
+pub fn script(code_hash: [u8; BLAKE2B_HASH_SIZE], args: &[u8]) -> Vec<u8> {
+    table(&[code_hash.to_vec(), vec![1], bytes(args)])
+}
+
+/// Molecule `WitnessArgs` with only the `lock` field set
+pub fn witness_args(lock: &[u8]) -> Vec<u8> {
+    table(&[bytes(lock), Vec::new(), Vec::new()])
+}
+
+// ============================================================================
+// Test Keys
+// ============================================================================
+
+/// A secp256k1 key that signs the way VoteSecure clients do
+pub struct TestKey(SigningKey);
+
+impl TestKey {
+    /// Deterministic key; distinct seeds give distinct keys
+    pub fn new(seed: u32) -> Self {
+        let mut scalar = [0u8; 32];
+        scalar[28..].copy_from_slice(&(seed + 1).to_be_bytes());
+        TestKey(SigningKey::from_slice(&scalar).expect("small nonzero scalar"))
+    }
+
+    pub fn pubkey(&self) -> [u8; PUBKEY_SIZE] {
+        let point = self.0.verifying_key().to_encoded_point(true);
+        point.as_bytes().try_into().expect("compressed point")
+    }
+
+    pub fn hash(&self) -> [u8; PUBKEY_HASH_SIZE] {
+        compute_pubkey_hash(&self.pubkey())
+    }
+
+    /// Low-s compact signature over a 32-byte message hash
+    pub fn sign(&self, message: &[u8; 32]) -> [u8; SIGNATURE_SIZE] {
+        let signature: Signature = self.0.sign_prehash(message).expect("prehash signature");
+        let signature = signature.normalize_s().unwrap_or(signature);
+        signature.to_bytes().into()
+    }
+
+    /// Signer entry: [pubkey: 33][signature: 64]
+    pub fn entry(&self, message: &[u8; 32]) -> Vec<u8> {
+        let mut entry = self.pubkey().to_vec();
+        entry.extend_from_slice(&self.sign(message));
+        entry
+    }
+}